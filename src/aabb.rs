@@ -0,0 +1,135 @@
+use crate::Interval;
+use crate::Ray;
+use crate::Vec3;
+
+/* Axis-aligned bounding box, stored as one interval per axis */
+#[derive(Copy, Clone, Default)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval
+}
+
+impl Aabb {
+    /* Treat the two points a and b as extrema for the bounding box, so we don't require a particular minimum/maximum coordinate order */
+    pub fn from_points(a: Vec3, b: Vec3) -> Aabb {
+        let mut bbox = Aabb {
+            x: Interval(a.x().min(b.x()), a.x().max(b.x())),
+            y: Interval(a.y().min(b.y()), a.y().max(b.y())),
+            z: Interval(a.z().min(b.z()), a.z().max(b.z()))
+        };
+        bbox.pad_to_minimums();
+        bbox
+    }
+
+    pub fn enclosing(box0: &Aabb, box1: &Aabb) -> Aabb {
+        Aabb {
+            x: Interval::enclosing(box0.x, box1.x),
+            y: Interval::enclosing(box0.y, box1.y),
+            z: Interval::enclosing(box0.z, box1.z)
+        }
+    }
+
+    pub fn axis_interval(&self, n: usize) -> Interval {
+        match n {
+            1 => self.y,
+            2 => self.z,
+            _ => self.x
+        }
+    }
+
+    /* 
+    ** Adjust the AABB so that no side is narrower than some delta, padding if necessary.
+    ** Flat objects (like a quad on an axis-aligned plane) would otherwise get a box with no thickness, which rays can miss.
+    */
+    fn pad_to_minimums(&mut self) {
+        let delta = 0.0001;
+        if self.x.size() < delta { self.x = self.x.expand(delta); }
+        if self.y.size() < delta { self.y = self.y.expand(delta); }
+        if self.z.size() < delta { self.z = self.z.expand(delta); }
+    }
+
+    /* Returns the index of the longest axis of the bounding box */
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() { 0 } else { 2 }
+        }
+        else if self.y.size() > self.z.size() { 1 } else { 2 }
+    }
+
+    /* 
+    ** Slab method: for each axis, find the ray "t" interval where the ray is between the two planes of that axis.
+    ** The ray hits the box only if the three intervals overlap.
+    */
+    pub fn hit(&self, ray: Ray, mut ray_t: Interval) -> bool {
+        let ray_orig = ray.origin();
+        let ray_dir = ray.direction();
+        let orig = [ray_orig.x(), ray_orig.y(), ray_orig.z()];
+        let dir = [ray_dir.x(), ray_dir.y(), ray_dir.z()];
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / dir[axis];
+
+            let t0 = (ax.min() - orig[axis]) * adinv;
+            let t1 = (ax.max() - orig[axis]) * adinv;
+
+            let (t_near, t_far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            if t_near > ray_t.min() { ray_t.0 = t_near; }
+            if t_far < ray_t.max() { ray_t.1 = t_far; }
+
+            if ray_t.max() <= ray_t.min() {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_and_miss() {
+        let bbox = Aabb::from_points(Vec3(1.0, 1.0, -3.0), Vec3(-1.0, -1.0, -2.0));
+
+        let towards = Ray {
+            orig: Vec3(0.0, 0.0, 0.0),
+            dir: Vec3(0.1, 0.1, -1.0)
+        };
+        let away = Ray {
+            orig: Vec3(0.0, 0.0, 0.0),
+            dir: Vec3(0.0, 0.0, 1.0)
+        };
+        let aside = Ray {
+            orig: Vec3(0.0, 0.0, 0.0),
+            dir: Vec3(1.0, 0.0, -1.0)
+        };
+
+        assert!(bbox.hit(towards, Interval(0.0, f64::INFINITY)));
+        assert!(!bbox.hit(away, Interval(0.0, f64::INFINITY)));
+        assert!(!bbox.hit(aside, Interval(0.0, f64::INFINITY)));
+        /* The box is out of reach if the interval ends before it */
+        assert!(!bbox.hit(towards, Interval(0.0, 1.0)));
+    }
+
+    #[test]
+    fn enclosing_and_longest_axis() {
+        let box0 = Aabb::from_points(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+        let box1 = Aabb::from_points(Vec3(-2.0, 0.5, 0.0), Vec3(0.0, 2.0, 0.5));
+
+        let bbox = Aabb::enclosing(&box0, &box1);
+
+        assert_float_absolute_eq!(bbox.x.min(), -2.0);
+        assert_float_absolute_eq!(bbox.y.max(), 2.0);
+        assert_float_absolute_eq!(bbox.z.max(), 1.0);
+        assert_eq!(bbox.longest_axis(), 0);
+    }
+}
@@ -0,0 +1,149 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::Interval;
+use crate::Ray;
+
+/* 
+** Bounding Volume Hierarchy: a binary tree of bounding boxes.
+** A ray that misses a node's box can't hit anything below it, so whole subtrees are skipped at once
+** and the intersection cost grows with log N instead of N.
+*/
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> BvhNode {
+        Self::from_objects(list.objects().to_vec())
+    }
+
+    /* An empty list gives a node with an empty box, which no ray hits */
+    pub fn from_objects(mut objects: Vec<Arc<dyn Hittable>>) -> BvhNode {
+        if objects.is_empty() {
+            let empty: Arc<dyn Hittable> = Arc::new(HittableList::default());
+            return BvhNode { left: empty.clone(), right: empty, bbox: Aabb::default() };
+        }
+
+        // Build the bounding box of the span of source objects.
+        let bbox = objects.iter().fold(Aabb::default(), |bbox, object| Aabb::enclosing(&bbox, &object.bounding_box()));
+
+        /* Split along the longest axis, so both halves end up as compact as possible */
+        let axis = bbox.longest_axis();
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            _ => {
+                objects.sort_by(|a, b| Self::box_compare(a, b, axis));
+
+                let right_half = objects.split_off(objects.len() / 2);
+                (Arc::new(Self::from_objects(objects)), Arc::new(Self::from_objects(right_half)))
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+
+    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
+        let a_axis_interval = a.bounding_box().axis_interval(axis);
+        let b_axis_interval = b.bounding_box().axis_interval(axis);
+        a_axis_interval.min().total_cmp(&b_axis_interval.min())
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        if !self.bbox.hit(ray, ray_t) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(ray, ray_t, hit_record);
+        /* If the left child was hit, the right one only matters if it is hit closer */
+        let right_max = if hit_left { hit_record.t } else { ray_t.max() };
+        let hit_right = self.right.hit(ray, Interval(ray_t.min(), right_max), hit_record);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::mesh::{Mesh, MeshData};
+    use crate::random::Rng;
+    use crate::sphere::Sphere;
+    use crate::Vec3;
+
+    fn random_point(rng: &mut Rng, min: f64, max: f64) -> Vec3 {
+        Vec3(rng.random_within(min, max), rng.random_within(min, max), rng.random_within(min, max))
+    }
+
+    fn random_spheres(count: usize, rng: &mut Rng) -> HittableList {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let mut list = HittableList::default();
+        for _ in 0..count {
+            list.add(Arc::new(Sphere {
+                center: random_point(rng, -10.0, 10.0),
+                radius: rng.random_within(0.1, 1.0),
+                material: material.clone()
+            }));
+        }
+        list
+    }
+
+    #[test]
+    fn same_hits_as_a_list() {
+        let mut rng = Rng::new(3);
+        let list = random_spheres(100, &mut rng);
+        let bvh = BvhNode::new(random_spheres(100, &mut Rng::new(3)));
+        let (mut hits, mut misses) = (0, 0);
+
+        for _ in 0..2000 {
+            let ray = Ray {
+                orig: random_point(&mut rng, -15.0, 15.0),
+                dir: random_point(&mut rng, -1.0, 1.0)
+            };
+            let mut from_list = HitRecord::default();
+            let mut from_bvh = HitRecord::default();
+
+            let hit = list.hit(ray, Interval(0.001, f64::INFINITY), &mut from_list);
+            assert_eq!(bvh.hit(ray, Interval(0.001, f64::INFINITY), &mut from_bvh), hit);
+            if hit {
+                assert_eq!(from_bvh.t, from_list.t);
+                assert_eq!(from_bvh.point, from_list.point);
+                hits += 1;
+            }
+            else {
+                misses += 1;
+            }
+        }
+
+        /* Both cases must actually be exercised */
+        assert!(hits > 100 && misses > 100);
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = BvhNode::new(HittableList::default());
+        let ray = Ray { orig: Vec3(0.0, 0.0, 0.0), dir: Vec3(0.0, 0.0, -1.0) };
+
+        assert!(!bvh.hit(ray, Interval(0.001, f64::INFINITY), &mut HitRecord::default()));
+
+        let mesh = Mesh::new(MeshData::default());
+        assert!(!mesh.hit(ray, Interval(0.001, f64::INFINITY), &mut HitRecord::default()));
+    }
+}
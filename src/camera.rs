@@ -1,54 +1,167 @@
-use std::fs::File;
-use std::io::Write;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 use crate::{Hittable, HitRecord};
-use crate::{Interval, INFINITY};
+use crate::environment::Background;
+use crate::image::Image;
+use crate::Interval;
 use crate::Vec3;
 use crate::Ray;
-use crate::random::random;
+use crate::random::Rng;
+use crate::sampler::{sample_uniform_disk, Sampler, SamplerType};
+
+/* Camera settings that leave the u, v, w basis undefined (NaN), which would render every pixel black */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    /* look_from and look_at are the same point, so there is no view direction */
+    SamePoints,
+    /* vup is parallel to the view direction (or zero), so it doesn't tell which way is up */
+    VupParallelToView
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::SamePoints => write!(f, "look_from and look_at must be different points"),
+            CameraError::VupParallelToView => write!(f, "vup must not be parallel to the view direction")
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
 
-#[derive(Default)]
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i64,
     pub samples_per_pixel: u32,
     pub max_depth: u32, // Max number of bounces of a ray into scene
+    pub vfov: f64, // Vertical view angle (field of view), in degrees
+    pub look_from: Vec3, // Point camera is looking from
+    pub look_at: Vec3, // Point camera is looking at
+    pub vup: Vec3, // Camera-relative "up" direction
+    pub defocus_angle: f64, // Variation angle of rays through each pixel, in degrees
+    pub focus_dist: f64, // Distance from camera look_from point to plane of perfect focus
+    pub threads: usize, // Number of render threads. 0 uses every available core
+    pub background: Background, // Scene color seen by rays that hit nothing
+    pub seed: Option<u64>, // Fixed seed for bit-reproducible renders. None picks a new one every run
+    pub sampler: SamplerType, // How the samples of a pixel are spread
     image_height: i64,
     camera_center: Vec3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
     pixel00_loc: Vec3,
-    pixel_samples_scale: f64
+    pixel_samples_scale: f64,
+    /* Camera frame basis vectors */
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    defocus_disk_u: Vec3, // Defocus disk horizontal radius
+    defocus_disk_v: Vec3 // Defocus disk vertical radius
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            aspect_ratio: 1.0,
+            image_width: 100,
+            samples_per_pixel: 10,
+            max_depth: 10,
+            vfov: 90.0,
+            look_from: Vec3(0.0, 0.0, 0.0),
+            look_at: Vec3(0.0, 0.0, -1.0),
+            vup: Vec3(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            threads: 0,
+            background: Background::default(),
+            seed: None,
+            sampler: SamplerType::default(),
+            image_height: 0,
+            camera_center: Vec3::default(),
+            pixel_delta_u: Vec3::default(),
+            pixel_delta_v: Vec3::default(),
+            pixel00_loc: Vec3::default(),
+            pixel_samples_scale: 0.0,
+            u: Vec3::default(),
+            v: Vec3::default(),
+            w: Vec3::default(),
+            defocus_disk_u: Vec3::default(),
+            defocus_disk_v: Vec3::default()
+        }
+    }
 }
 
 impl Camera {
-    pub fn render<T: Hittable>(mut self, world: T, mut f: File) {
+    /* Checks that look_from, look_at and vup define a camera basis. render() does it before anything else */
+    pub fn validate(&self) -> Result<(), CameraError> {
+        let view = self.look_from - self.look_at;
+        if view.near_zero() {
+            return Err(CameraError::SamePoints);
+        }
+        if self.vup.cross(view).near_zero() {
+            return Err(CameraError::VupParallelToView);
+        }
+
+        Ok(())
+    }
+
+    /* Renders the world into a framebuffer of linear radiance. Use an encoder (e.g. ppm::write_p3) to store it */
+    pub fn render<T: Hittable>(mut self, world: T) -> Result<Image, CameraError> {
+        self.validate()?;
         self.initialize();
 
-        writeln!(f, "P3").unwrap();
-        writeln!(f, "{0} {1}", self.image_width, self.image_height).unwrap();
-        writeln!(f, "255").unwrap();
-    
-        let (mut j, mut i) = (0, 0);
-    
-        while j < self.image_height {
-            i = 0;
-            println!("\rScanlines remaining: {}", self.image_height - j);
-            while i < self.image_width {
-                let mut pixel_color = Vec3(0.0, 0.0, 0.0);
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color = pixel_color + Self::ray_color(ray, self.max_depth, &world);
-                }
+        /* Each worker grabs the next unrendered scanline from the shared iterator and writes its row of the framebuffer.
+        ** The rows are disjoint slices, so only picking the next row needs the lock.
+        */
+        let mut image = Image::new(self.image_width as usize, self.image_height as usize);
+        let scanlines = Mutex::new(image.rows_mut().enumerate());
+        let remaining = AtomicI64::new(self.image_height);
+        let seed = self.seed.unwrap_or_else(|| Rng::from_entropy().next_u64());
 
-                Self::write_color(self.pixel_samples_scale * pixel_color, &mut f);
-    
-                i += 1;
+        thread::scope(|scope| {
+            for _ in 0..self.thread_count() {
+                scope.spawn(|| loop {
+                    let next = scanlines.lock().unwrap().next();
+                    let Some((j, row)) = next else { break };
+
+                    self.render_scanline(j as i64, row, &world, seed);
+
+                    let left = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                    println!("\rScanlines remaining: {}", left);
+                });
             }
-            j += 1;
-        }
-    
+        });
+
         println!("\rDone.                 ");
+
+        Ok(image)
+    }
+
+    fn render_scanline<T: Hittable>(&self, j: i64, row: &mut [Vec3], world: &T, seed: u64) {
+        let mut sampler = self.sampler.create(self.samples_per_pixel, seed);
+
+        for (i, pixel) in row.iter_mut().enumerate() {
+            let mut pixel_color = Vec3(0.0, 0.0, 0.0);
+            for sample in 0..self.samples_per_pixel {
+                /* The samples only depend on the pixel and the seed, not on which thread renders it */
+                sampler.start_pixel_sample((i, j as usize), sample);
+
+                let ray = self.get_ray(i as i64, j, sampler.as_mut());
+                pixel_color += self.ray_color(ray, self.max_depth, world, sampler.as_mut());
+            }
+
+            *pixel = self.pixel_samples_scale * pixel_color;
+        }
+    }
+
+    fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+
+        thread::available_parallelism().map_or(1, |n| n.get())
     }
 
     fn initialize(&mut self) {
@@ -56,35 +169,52 @@ impl Camera {
         // Calculate the image height, and ensure that it's at least 1.
         self.image_height = ((self.image_width as f64 / self.aspect_ratio) as i64).max(1);
 
-        // Camera definitions 
-        let focal_length = 1.0;
-        let viewport_height = 2.0;
+        self.camera_center = self.look_from;
+
+        /* Determine viewport dimensions.
+        ** The viewport is placed at the focus plane, so everything at focus_dist is in perfect focus
+        */
+        /* theta is the vertical field of view. h is half the viewport height, at unit distance */
+        let theta = self.vfov.to_radians();
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h * self.focus_dist;
         let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
-        self.camera_center = Vec3(0.0, 0.0, 0.0);
 
         /* Weight for antialiasing */
         self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
 
+        /* Calculate the u,v,w unit basis vectors for the camera coordinate frame.
+        ** w points opposite to the view direction, u points to the camera right and v to the camera up
+        */
+        self.w = (self.look_from - self.look_at).unit();
+        self.u = self.vup.cross(self.w).unit();
+        self.v = self.w.cross(self.u);
+
         // Calculate the vectors across the horizontal and down the vertical viewport edges.
-        let viewport_u = Vec3(viewport_width, 0.0, 0.0);
+        let viewport_u = viewport_width * self.u;
 
-        let viewport_v = Vec3(0.0, -viewport_height, 0.0);
+        let viewport_v = viewport_height * -self.v;
 
         // Calculate the horizontal and vertical delta vectors from pixel to pixel.
         self.pixel_delta_u = viewport_u / self.image_width as f64;
         self.pixel_delta_v = viewport_v / self.image_height as f64;
 
         // Calculate the location of the upper left pixel.
-        let focal_length_vector = Vec3(0.0, 0.0, focal_length);
-
-        let viewport_upper_left = self.camera_center - focal_length_vector - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_upper_left = self.camera_center - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
         self.pixel00_loc = viewport_upper_left + (self.pixel_delta_u + self.pixel_delta_v) / 2.0;
+
+        /* Calculate the camera defocus disk basis vectors.
+        ** The disk is the base of a cone with apex at the focus plane and aperture angle defocus_angle
+        */
+        let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
+        self.defocus_disk_u = defocus_radius * self.u;
+        self.defocus_disk_v = defocus_radius * self.v;
     
     }
 
-    fn ray_color<T: Hittable>(r: Ray, depth: u32, world: &T) -> Vec3 {
+    fn ray_color<T: Hittable>(&self, r: Ray, depth: u32, world: &T, sampler: &mut dyn Sampler) -> Vec3 {
         // If we've exceeded the ray bounce limit, no more light is gathered.
-        if depth <= 0 {
+        if depth == 0 {
             return Vec3(0.0, 0.0, 0.0);
         }
 
@@ -93,59 +223,78 @@ impl Camera {
         ** Someone brought a discussion about this possibly being wrong:
         ** https://github.com/RayTracing/raytracing.github.io/discussions/1296
          */
-        if world.hit(r, Interval(0.0001, INFINITY), &mut hit_record) {
+        if world.hit(r, Interval(0.0001, f64::INFINITY), &mut hit_record) {
             let mat = hit_record.material.clone().unwrap(); 
+            let color_from_emission = mat.emitted(hit_record.u, hit_record.v, hit_record.point);
 
-            match mat.scatter(&r, &hit_record) {
+            match mat.scatter(&r, &hit_record, sampler) {
                 Some((scattered,attenuation)) => {
-                    return attenuation * Self::ray_color(scattered, depth - 1, world)
+                    let (scattered, weight) = self.sample_environment(&r, &hit_record, scattered, sampler);
+                    if weight == 0.0 {
+                        return color_from_emission
+                    }
+                    let color_from_scatter = weight * attenuation * self.ray_color(scattered, depth - 1, world, sampler);
+                    return color_from_emission + color_from_scatter
                 }
                 None => {
-                    return Vec3(0.0, 0.0, 0.0)
+                    return color_from_emission
                 }
             }
         }
     
-        let unit_direction = r.direction().unit();
-        let a = 0.5 * (unit_direction.y() + 1.0);
-    
-        // Calculate the "lerp". a = 1, color = blue. a = 0, color = blue
-        let white = Vec3(1.0, 1.0, 1.0);
-    
-        let blue = Vec3(0.5, 0.7, 1.0);
-    
-        let blended_value = (1.0 - a) * white + a * blue;
-        blended_value
+        // If the ray hits nothing, return the background color.
+        self.background.value(r.direction())
     }
 
-    fn write_color(pixel_color: Vec3, f: &mut File) {
-        let r = pixel_color.x();
-        let g = pixel_color.y();
-        let b = pixel_color.z();
+    /*
+    ** For diffuse bounces, half of the time we aim the scattered ray at the environment (e.g. towards the sun of an HDRI)
+    ** instead of using the material's own direction. Both choices are weighted by the density of the mixture,
+    ** so the average stays the same as with material sampling alone, but with much less noise.
+    ** Returns the ray to follow and the weight to apply on top of the material attenuation.
+    */
+    fn sample_environment(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: Ray, sampler: &mut dyn Sampler) -> (Ray, f64) {
+        let mat = hit_record.material.clone().unwrap();
+        if mat.scattering_pdf(ray_in, hit_record, &scattered) <= 0.0 {
+            return (scattered, 1.0);
+        }
 
-        // Apply a linear to gamma transform for gamma 2
-        let r = Self::linear_to_gamma(r);
-        let g = Self::linear_to_gamma(g);
-        let b = Self::linear_to_gamma(b);
-    
-        // Translate the [0,1] component values to the byte range [0,255].
-        let intensity = Interval(0.000, 0.999);
-        let rbyte = (255.999 * intensity.clamp(r)) as i64;
-        let gbyte = (255.999 * intensity.clamp(g)) as i64;
-        let bbyte = (255.999 * intensity.clamp(b)) as i64;
-    
-        writeln!(f, "{} {} {}", rbyte, gbyte, bbyte).unwrap();
+        let (u1, u2) = sampler.get_2d();
+        let choice = sampler.get_1d();
+        let Some((env_direction, _)) = self.background.sample(u1, u2) else {
+            return (scattered, 1.0);
+        };
+
+        let scattered = if choice < 0.5 {
+            Ray {
+                orig: hit_record.point,
+                dir: env_direction
+            }
+        }
+        else {
+            scattered
+        };
+
+        let material_pdf = mat.scattering_pdf(ray_in, hit_record, &scattered);
+        let mixture_pdf = 0.5 * material_pdf + 0.5 * self.background.pdf(scattered.direction());
+        if mixture_pdf <= 0.0 {
+            return (scattered, 0.0);
+        }
+
+        (scattered, material_pdf / mixture_pdf)
     }
 
-    // Construct a camera ray originating from the origin and directed at randomly sampled
+    // Construct a camera ray originating from the defocus disk and directed at randomly sampled
     // point around the pixel location i, j.
-    fn get_ray(&self, i: i64, j: i64) -> Ray {
-        let offset = Vec3(random() - 0.5, random() - 0.5, 0.0);
+    fn get_ray(&self, i: i64, j: i64, sampler: &mut dyn Sampler) -> Ray {
+        let (x, y) = sampler.get_2d();
+        let offset = Vec3(x - 0.5, y - 0.5, 0.0);
+        /* Drawn even without defocus, so the following dimensions are always the same ones */
+        let lens = sampler.get_2d();
 
         let pixel_sample = self.pixel00_loc 
                                 + ((i as f64 + offset.x()) * self.pixel_delta_u)
                                 + ((j as f64 + offset.y()) * self.pixel_delta_v);
-        let ray_origin = self.camera_center;
+        let ray_origin = if self.defocus_angle <= 0.0 { self.camera_center } else { self.defocus_disk_sample(lens) };
         let ray_direction = pixel_sample - ray_origin;
 
         Ray {
@@ -154,14 +303,138 @@ impl Camera {
         }
     }
 
-    /* Transform from "linear space"  to "gamma space, a collor correction commonly expected when storing images
-    ** This will represent color intensity more accurately
-    */
-    fn linear_to_gamma(linear_component: f64) -> f64 {
-        if linear_component > 0.0 { 
-            return linear_component.sqrt();
+    // Returns a random point in the camera defocus disk.
+    fn defocus_disk_sample(&self, lens: (f64, f64)) -> Vec3 {
+        let p = sample_uniform_disk(lens);
+        self.camera_center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
+    }
+}
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use crate::aabb::Aabb;
+    use crate::material::{Dielectric, Lambertian, Metal};
+    use crate::sphere::Sphere;
+    use crate::HittableList;
+
+    fn render(seed: Option<u64>, threads: usize, sampler: SamplerType) -> Image {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere { center: Vec3(0.0, -100.5, -1.0), radius: 100.0, material: Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))) }));
+        world.add(Arc::new(Sphere { center: Vec3(-0.6, 0.0, -1.0), radius: 0.5, material: Arc::new(Dielectric { refraction_index: 1.5 }) }));
+        world.add(Arc::new(Sphere { center: Vec3(0.6, 0.0, -1.0), radius: 0.5, material: Arc::new(Metal { albedo: Vec3(0.8, 0.6, 0.2), fuzz: 0.3 }) }));
+
+        let camera = Camera {
+            image_width: 16,
+            samples_per_pixel: 4,
+            defocus_angle: 2.0,
+            focus_dist: 1.0,
+            threads,
+            seed,
+            sampler,
+            ..Default::default()
+        };
+        camera.render(world).unwrap()
+    }
+
+    #[test]
+    fn seeded_renders_are_reproducible() {
+        for sampler in [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+            let single = render(Some(42), 1, sampler);
+
+            /* Bit for bit, whatever the number of threads */
+            assert!(single.pixels() == render(Some(42), 1, sampler).pixels());
+            assert!(single.pixels() == render(Some(42), 4, sampler).pixels());
+            assert!(single.pixels() != render(Some(43), 1, sampler).pixels());
+        }
+    }
+
+    /* Never hit, counts the rays it was asked about */
+    struct CountRays(AtomicUsize);
+
+    impl Hittable for CountRays {
+        fn hit(&self, _ray: Ray, _ray_t: Interval, _hit_record: &mut HitRecord) -> bool {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            false
         }
 
-        return 0.0;
+        fn bounding_box(&self) -> Aabb {
+            Aabb::default()
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn threads_render_every_row_once() {
+        let background = Vec3(0.25, 0.5, 0.75);
+        let rays = Arc::new(CountRays(AtomicUsize::new(0)));
+        let mut world = HittableList::default();
+        world.add(rays.clone());
+        let camera = Camera {
+            image_width: 24,
+            aspect_ratio: 24.0 / 37.0,
+            samples_per_pixel: 3,
+            threads: 5,
+            background: Background::Solid(background),
+            ..Default::default()
+        };
+
+        let image = camera.render(world).unwrap();
+
+        /* A skipped row would stay black, and a row rendered twice would trace extra rays */
+        assert_eq!(image.height(), 37);
+        assert!(image.pixels().iter().all(|&pixel| pixel == background));
+        assert_eq!(rays.0.load(Ordering::Relaxed), 24 * 37 * 3);
+    }
+
+    #[test]
+    fn thin_lens_ray_origins() {
+        let mut camera = Camera {
+            look_from: Vec3(1.0, 2.0, 3.0),
+            look_at: Vec3(1.0, 2.0, 0.0),
+            focus_dist: 2.0,
+            ..Default::default()
+        };
+        let mut sampler = SamplerType::Independent.create(64, 7);
+
+        /* Pinhole: every ray starts on the camera center */
+        camera.initialize();
+        for sample in 0..64 {
+            sampler.start_pixel_sample((5, 5), sample);
+            assert_eq!(camera.get_ray(5, 5, sampler.as_mut()).origin(), camera.look_from);
+        }
+
+        /* Thin lens: the origins spread over the disk facing the view direction */
+        camera.defocus_angle = 10.0;
+        camera.initialize();
+        let radius = camera.focus_dist * 5.0_f64.to_radians().tan();
+        let mut farthest: f64 = 0.0;
+        for sample in 0..64 {
+            sampler.start_pixel_sample((5, 5), sample);
+            let offset = camera.get_ray(5, 5, sampler.as_mut()).origin() - camera.look_from;
+
+            assert!(offset.length() <= radius + 1e-12);
+            assert_float_absolute_eq!(offset.dot(camera.w), 0.0);
+            farthest = farthest.max(offset.length());
+        }
+        assert!(farthest > radius / 2.0);
+    }
+
+    #[test]
+    fn degenerate_basis() {
+        let camera = |look_from, vup| Camera { look_from, look_at: Vec3(0.0, 0.0, 0.0), vup, ..Default::default() };
+
+        assert_eq!(camera(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)).validate(), Err(CameraError::SamePoints));
+        assert_eq!(camera(Vec3(0.0, 5.0, 0.0), Vec3(0.0, 1.0, 0.0)).validate(), Err(CameraError::VupParallelToView));
+        assert_eq!(camera(Vec3(0.0, 0.0, 1.0), Vec3(0.0, 0.0, 0.0)).validate(), Err(CameraError::VupParallelToView));
+        assert_eq!(camera(Vec3(0.0, 5.0, 0.0), Vec3(0.0, 0.0, -1.0)).validate(), Ok(()));
+
+        /* render() refuses it instead of returning a black image */
+        let camera = camera(Vec3(0.0, -2.0, 0.0), Vec3(0.0, 1.0, 0.0));
+        assert_eq!(camera.render(HittableList::default()).err(), Some(CameraError::VupParallelToView));
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use rust_ray_tracing::{Camera, ImageFormat, SamplerType};
+
+pub const USAGE: &str = "\
+Usage: rust-ray-tracing [OPTIONS]
+
+Renders a scene file, or the built-in demo scene when no file is given.
+
+Options:
+  -s, --scene <FILE>         JSON scene to render
+  -o, --output <FILE>        Output image [default: image.ppm]
+  -f, --format <FORMAT>      ppm, ppm-ascii, png, png16, pfm or hdr [default: from the output extension]
+  -w, --width <PIXELS>       Image width
+  -a, --aspect-ratio <RATIO> Width over height, as a number (1.5) or a ratio (16:9)
+  -n, --samples <COUNT>      Samples per pixel
+  -d, --max-depth <COUNT>    Max number of bounces of a ray
+  -t, --threads <COUNT>      Render threads, 0 for every core
+      --seed <NUMBER>        Seed for a reproducible render
+      --sampler <SAMPLER>    independent, stratified, halton or sobol
+  -h, --help                 Print this help
+
+Options given here override the camera settings of the scene.";
+
+/* What the user asked for on the command line */
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Render(Options),
+    Help
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub scene: Option<PathBuf>,
+    pub output: PathBuf,
+    pub format: ImageFormat,
+    /* Camera overrides. None keeps the scene's value */
+    pub width: Option<i64>,
+    pub aspect_ratio: Option<f64>,
+    pub samples: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub sampler: Option<SamplerType>
+}
+
+impl Options {
+    pub fn apply(&self, camera: &mut Camera) {
+        if let Some(width) = self.width {
+            camera.image_width = width;
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera.aspect_ratio = aspect_ratio;
+        }
+        if let Some(samples) = self.samples {
+            camera.samples_per_pixel = samples;
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera.max_depth = max_depth;
+        }
+        if let Some(threads) = self.threads {
+            camera.threads = threads;
+        }
+        if self.seed.is_some() {
+            camera.seed = self.seed;
+        }
+        if let Some(sampler) = self.sampler {
+            camera.sampler = sampler;
+        }
+    }
+}
+
+/* Parses the arguments, without the program name. Values follow their flag, as "--width 800" or "--width=800" */
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+
+    let mut scene = None;
+    let mut output = PathBuf::from("image.ppm");
+    let mut format = None;
+    let mut width = None;
+    let mut aspect_ratio = None;
+    let mut samples = None;
+    let mut max_depth = None;
+    let mut threads = None;
+    let mut seed = None;
+    let mut sampler = None;
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None)
+        };
+        let mut value = || inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("{flag} needs a value"));
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-s" | "--scene" => scene = Some(PathBuf::from(value()?)),
+            "-o" | "--output" => output = PathBuf::from(value()?),
+            "-f" | "--format" => {
+                let name = value()?;
+                format = Some(ImageFormat::from_name(&name).ok_or_else(|| format!("unknown image format \"{name}\""))?);
+            }
+            "-w" | "--width" => width = Some(positive::<i64>(&flag, &value()?)?),
+            "-a" | "--aspect-ratio" => aspect_ratio = Some(parse_aspect_ratio(&value()?)?),
+            "-n" | "--samples" => samples = Some(positive::<u32>(&flag, &value()?)?),
+            "-d" | "--max-depth" => max_depth = Some(positive::<u32>(&flag, &value()?)?),
+            "-t" | "--threads" => threads = Some(number::<usize>(&flag, &value()?)?),
+            "--seed" => seed = Some(number::<u64>(&flag, &value()?)?),
+            "--sampler" => {
+                let name = value()?;
+                sampler = Some(SamplerType::from_name(&name).ok_or_else(|| format!("unknown sampler \"{name}\""))?);
+            }
+            _ => return Err(format!("unknown option \"{arg}\""))
+        }
+    }
+
+    let format = match format {
+        Some(format) => format,
+        None => ImageFormat::from_path(&output)
+            .ok_or_else(|| format!("can't tell the image format of \"{}\", use --format", output.display()))?
+    };
+
+    Ok(Command::Render(Options { scene, output, format, width, aspect_ratio, samples, max_depth, threads, seed, sampler }))
+}
+
+fn number<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{flag}: \"{value}\" is not a valid number"))
+}
+
+fn positive<T: FromStr + PartialOrd + Default>(flag: &str, value: &str) -> Result<T, String> {
+    let number = number::<T>(flag, value)?;
+    if number <= T::default() {
+        return Err(format!("{flag} must be greater than 0"));
+    }
+    Ok(number)
+}
+
+/* "1.5" or "16:9" */
+fn parse_aspect_ratio(value: &str) -> Result<f64, String> {
+    let ratio = match value.split_once(':') {
+        Some((width, height)) => number::<f64>("--aspect-ratio", width)? / number::<f64>("--aspect-ratio", height)?,
+        None => number::<f64>("--aspect-ratio", value)?
+    };
+
+    if !ratio.is_finite() || ratio <= 0.0 {
+        return Err(format!("--aspect-ratio: \"{value}\" is not a positive ratio"));
+    }
+    Ok(ratio)
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_ray_tracing::BitDepth;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("expected render options, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let defaults = options(&[]);
+
+        assert_eq!(defaults.scene, None);
+        assert_eq!(defaults.output, PathBuf::from("image.ppm"));
+        assert_eq!(defaults.format, ImageFormat::PpmBinary);
+        assert_eq!(defaults.width, None);
+        assert_eq!(defaults.seed, None);
+        assert_eq!(parse(&["--width", "10", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn flags() {
+        let parsed = options(&[
+            "--scene", "scenes/three_spheres.json", "-o", "out/render.hdr", "--width=800", "-a", "16:9",
+            "--samples", "64", "-d", "8", "--threads=0", "--seed", "42", "--sampler", "halton"
+        ]);
+
+        assert_eq!(parsed.scene, Some(PathBuf::from("scenes/three_spheres.json")));
+        assert_eq!(parsed.output, PathBuf::from("out/render.hdr"));
+        assert_eq!(parsed.format, ImageFormat::Hdr);
+        assert_eq!(parsed.width, Some(800));
+        assert_float_absolute_eq!(parsed.aspect_ratio.unwrap(), 16.0 / 9.0);
+        assert_eq!(parsed.samples, Some(64));
+        assert_eq!(parsed.max_depth, Some(8));
+        assert_eq!(parsed.threads, Some(0));
+        assert_eq!(parsed.seed, Some(42));
+        assert_eq!(parsed.sampler, Some(SamplerType::Halton));
+
+        /* An explicit format wins over the extension */
+        assert_eq!(options(&["-o", "render.img", "-f", "png16"]).format, ImageFormat::Png(BitDepth::Sixteen));
+        assert_eq!(options(&["-o", "render.ppm"]).format, ImageFormat::PpmBinary);
+        assert_eq!(options(&["-o", "render.ppm", "-f", "ppm-ascii"]).format, ImageFormat::PpmAscii);
+
+        let mut camera = Camera::default();
+        parsed.apply(&mut camera);
+        assert_eq!((camera.image_width, camera.samples_per_pixel, camera.max_depth, camera.seed), (800, 64, 8, Some(42)));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse(&["--width"]), Err("--width needs a value".to_string()));
+        assert_eq!(parse(&["--width", "0"]), Err("--width must be greater than 0".to_string()));
+        assert_eq!(parse(&["-n", "many"]), Err("-n: \"many\" is not a valid number".to_string()));
+        assert_eq!(parse(&["--fast"]), Err("unknown option \"--fast\"".to_string()));
+        assert_eq!(parse(&["-f", "jpeg"]), Err("unknown image format \"jpeg\"".to_string()));
+        assert_eq!(parse(&["-o", "render.jpg"]), Err("can't tell the image format of \"render.jpg\", use --format".to_string()));
+        assert!(parse(&["--aspect-ratio", "16:0"]).is_err());
+        assert_eq!(parse(&["--sampler", "blue-noise"]), Err("unknown sampler \"blue-noise\"".to_string()));
+    }
+}
@@ -0,0 +1,150 @@
+/*
+** Piecewise-constant probability distributions, used to draw samples proportionally to a tabulated function
+** (e.g. the brightness of an environment map) instead of uniformly.
+** Ref: https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/Sampling_Random_Variables#Example:Piecewise-Constant1DFunctions
+*/
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64
+}
+
+impl Distribution1D {
+    /* The function values are taken as constant over n equal steps covering [0, 1). Negative values count as 0 */
+    pub fn new(func: &[f64]) -> Distribution1D {
+        let n = func.len();
+        let func: Vec<f64> = func.iter().map(|&f| f.max(0.0)).collect();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f64;
+        }
+        let func_int = cdf[n];
+
+        /* A function that is 0 everywhere gets sampled uniformly */
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if func_int == 0.0 { i as f64 / n as f64 } else { *c / func_int };
+        }
+
+        Distribution1D { func, cdf, func_int }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /* Integral of the function over [0, 1) */
+    pub fn integral(&self) -> f64 {
+        self.func_int
+    }
+
+    /* 
+    ** Maps a uniform u in [0, 1) to x in [0, 1) distributed like the function, by inverting the CDF.
+    ** Returns x, its density and the index of the step it falls into.
+    */
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        // Find the last cdf entry that is <= u.
+        let offset = self.cdf.partition_point(|&c| c <= u).clamp(1, self.count()) - 1;
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x, self.pdf_at(offset), offset)
+    }
+
+    /* Density of the step with the given index */
+    pub fn pdf_at(&self, offset: usize) -> f64 {
+        if self.func_int == 0.0 {
+            return 1.0;
+        }
+        self.func[offset] / self.func_int
+    }
+}
+
+/* 2D distribution over [0, 1)^2: pick a row with the marginal distribution, then a column within that row */
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D
+}
+
+impl Distribution2D {
+    /* func holds nu * nv values, row by row */
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = func.chunks(nu).take(nv).map(Distribution1D::new).collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|row| row.integral()).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func)
+        }
+    }
+
+    /* Returns (u, v) and the density of that point */
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u2);
+        let (u, pdf_u, _) = self.conditional[row].sample(u1);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let nu = self.conditional[0].count();
+        let nv = self.marginal.count();
+        let iu = ((u * nu as f64) as usize).min(nu - 1);
+        let iv = ((v * nv as f64) as usize).min(nv - 1);
+
+        self.marginal.pdf_at(iv) * self.conditional[iv].pdf_at(iu)
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_1d() {
+        let distribution = Distribution1D::new(&[0.0, 1.0, 3.0, 0.0]);
+
+        assert_float_absolute_eq!(distribution.integral(), 1.0);
+
+        /* A quarter of the mass is in the second step, the rest in the third one */
+        let (x, pdf, offset) = distribution.sample(0.125);
+        assert_eq!(offset, 1);
+        assert_float_absolute_eq!(x, 0.375);
+        assert_float_absolute_eq!(pdf, 1.0);
+
+        let (x, pdf, offset) = distribution.sample(0.625);
+        assert_eq!(offset, 2);
+        assert_float_absolute_eq!(x, 0.625);
+        assert_float_absolute_eq!(pdf, 3.0);
+    }
+
+    #[test]
+    fn all_zero_is_uniform() {
+        let distribution = Distribution1D::new(&[0.0, 0.0]);
+
+        let (x, pdf, _) = distribution.sample(0.3);
+        assert_float_absolute_eq!(x, 0.3);
+        assert_float_absolute_eq!(pdf, 1.0);
+    }
+
+    #[test]
+    fn sample_2d_matches_pdf() {
+        let func = [1.0, 2.0, 0.0, 5.0, 0.5, 0.5];
+        let distribution = Distribution2D::new(&func, 3, 2);
+
+        for (u1, u2) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.95), (0.0, 0.0)] {
+            let ((u, v), pdf) = distribution.sample(u1, u2);
+            assert_float_absolute_eq!(pdf, distribution.pdf(u, v));
+            assert!(pdf > 0.0);
+        }
+    }
+}
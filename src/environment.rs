@@ -0,0 +1,261 @@
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::distribution::Distribution2D;
+use crate::image::Image;
+use crate::ppm::invalid_data;
+use crate::Vec3;
+
+/* Radiance arriving from infinitely far away, looked up by the direction of a ray that escaped the scene */
+pub trait Environment: Send + Sync {
+    fn value(&self, direction: Vec3) -> Vec3;
+
+    /* 
+    ** Importance sampling: maps two uniform numbers to a unit direction, returned with its density (per solid angle).
+    ** Environments that can't do better than chance return None, and are only found by rays that happen to escape.
+    */
+    fn sample(&self, _u1: f64, _u2: f64) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /* Density of sample() generating the given direction */
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+}
+
+/* What a ray sees when it doesn't hit anything */
+#[derive(Clone)]
+pub enum Background {
+    Solid(Vec3),
+    /* Vertical blend from the bottom color (looking straight down) to the top color (looking straight up) */
+    Gradient { bottom: Vec3, top: Vec3 },
+    Environment(Arc<dyn Environment>)
+}
+
+/* The white-to-blue sky we've always rendered */
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Vec3(1.0, 1.0, 1.0),
+            top: Vec3(0.5, 0.7, 1.0)
+        }
+    }
+}
+
+impl Background {
+    pub fn value(&self, direction: Vec3) -> Vec3 {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let unit_direction = direction.unit();
+                let a = 0.5 * (unit_direction.y() + 1.0);
+
+                // Calculate the "lerp". a = 1, color = top. a = 0, color = bottom
+                (1.0 - a) * bottom + a * top
+            }
+            Background::Environment(environment) => environment.value(direction)
+        }
+    }
+
+    pub fn sample(&self, u1: f64, u2: f64) -> Option<(Vec3, f64)> {
+        match self {
+            Background::Environment(environment) => environment.sample(u1, u2),
+            _ => None
+        }
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        match self {
+            Background::Environment(environment) => environment.pdf(direction),
+            _ => 0.0
+        }
+    }
+}
+
+/*
+** Latitude-longitude (equirectangular) environment map, typically an HDRI photo of the surroundings.
+** The image's horizontal axis covers 360 degrees around the Y axis, with its center looking down -Z,
+** and its vertical axis goes from straight up (top row) to straight down (bottom row).
+*/
+pub struct EnvironmentMap {
+    image: Image,
+    rotation: f64, // Rotation around the Y axis, in radians
+    intensity: f64, // Multiplier of the image radiance
+    /* Pixels are sampled proportionally to their luminance, so the bright spots (like the sun) are found directly */
+    distribution: Distribution2D
+}
+
+impl EnvironmentMap {
+    /* rotation is in degrees. An empty image is an InvalidData error, as PPM and PFM headers may give a zero size */
+    pub fn new(image: Image, rotation: f64, intensity: f64) -> io::Result<EnvironmentMap> {
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Err(invalid_data(format!("empty environment map image ({}x{} pixels)", width, height)));
+        }
+
+        /* Rows near the poles cover less solid angle, so they are weighted by sin(theta) */
+        let mut func = Vec::with_capacity(width * height);
+        for j in 0..height {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            func.extend((0..width).map(|i| luminance(image.pixel(i, j)) * sin_theta));
+        }
+
+        Ok(EnvironmentMap {
+            distribution: Distribution2D::new(&func, width, height),
+            image,
+            rotation: rotation.to_radians(),
+            intensity
+        })
+    }
+
+    /* Loads a .hdr, .pfm or .ppm image */
+    pub fn load<P: AsRef<Path>>(path: P, rotation: f64, intensity: f64) -> io::Result<EnvironmentMap> {
+        Self::new(Image::load(path)?, rotation, intensity)
+    }
+
+    /* Image coordinates in [0, 1)^2 of a direction */
+    fn direction_to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let unit = direction.unit();
+        let theta = unit.y().clamp(-1.0, 1.0).acos();
+        let phi = unit.x().atan2(-unit.z()) - self.rotation;
+
+        let u = (phi + PI) / (2.0 * PI);
+        (u - u.floor(), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let theta = PI * v;
+        let phi = 2.0 * PI * u - PI + self.rotation;
+
+        Vec3(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn value(&self, direction: Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        let i = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let j = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+
+        self.intensity * self.image.pixel(i, j)
+    }
+
+    fn sample(&self, u1: f64, u2: f64) -> Option<(Vec3, f64)> {
+        let ((u, v), pdf_uv) = self.distribution.sample(u1, u2);
+        let sin_theta = (PI * v).sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+
+        /* The image is stretched over the sphere: d(solid angle) = 2 pi^2 sin(theta) du dv */
+        Some((self.uv_to_direction(u, v), pdf_uv / (2.0 * PI * PI * sin_theta)))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/* Perceived brightness of a linear RGB color (Rec. 709 weights) */
+pub fn luminance(color: Vec3) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Directional;
+
+    impl Environment for Directional {
+        fn value(&self, direction: Vec3) -> Vec3 {
+            direction
+        }
+    }
+
+    #[test]
+    fn background_values() {
+        let sky = Background::default();
+        assert_eq!(sky.value(Vec3(0.0, 3.0, 0.0)), Vec3(0.5, 0.7, 1.0));
+        assert_eq!(sky.value(Vec3(0.0, -1.0, 0.0)), Vec3(1.0, 1.0, 1.0));
+
+        let black = Background::Solid(Vec3(0.0, 0.0, 0.0));
+        assert_eq!(black.value(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, 0.0));
+
+        let environment = Background::Environment(Arc::new(Directional));
+        assert_eq!(environment.value(Vec3(1.0, 2.0, 3.0)), Vec3(1.0, 2.0, 3.0));
+        assert!(environment.sample(0.5, 0.5).is_none());
+    }
+
+    /* A dim map with a single bright pixel, our "sun" */
+    fn sun_map(rotation: f64) -> EnvironmentMap {
+        let mut image = Image::new(16, 8);
+        for j in 0..8 {
+            for i in 0..16 {
+                image.set_pixel(i, j, Vec3(0.1, 0.1, 0.1));
+            }
+        }
+        image.set_pixel(12, 2, Vec3(1000.0, 900.0, 800.0));
+
+        EnvironmentMap::new(image, rotation, 2.0).unwrap()
+    }
+
+    #[test]
+    fn direction_mapping() {
+        let map = sun_map(90.0);
+
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.8, 0.9)] {
+            let direction = map.uv_to_direction(u, v);
+            let (u2, v2) = map.direction_to_uv(direction);
+            assert_float_absolute_eq!(u, u2);
+            assert_float_absolute_eq!(v, v2);
+        }
+
+        /* Straight up is the top row; intensity scales the radiance */
+        assert_eq!(map.value(Vec3(0.0, 1.0, 0.0)), Vec3(0.2, 0.2, 0.2));
+    }
+
+    #[test]
+    fn importance_sampling() {
+        let map = sun_map(30.0);
+
+        let mut sun_samples = 0;
+        for k in 0..100 {
+            let (u1, u2) = ((k % 10) as f64 / 10.0 + 0.05, (k / 10) as f64 / 10.0 + 0.05);
+            let (direction, pdf) = map.sample(u1, u2).unwrap();
+
+            assert_float_relative_eq!(pdf, map.pdf(direction), 1e-6);
+            if map.value(direction).x() > 1.0 {
+                sun_samples += 1;
+            }
+        }
+
+        /* The sun is 1 pixel out of 128, but it has most of the energy */
+        assert!(sun_samples > 90);
+    }
+
+    #[test]
+    fn empty_image() {
+        let path = std::env::temp_dir().join(format!("environment-test-{}.ppm", std::process::id()));
+
+        /* Valid PPM headers, without a single pixel */
+        for header in ["P3\n0 4\n255\n", "P6\n4 0\n255\n"] {
+            std::fs::write(&path, header).unwrap();
+            assert_eq!(EnvironmentMap::load(&path, 0.0, 1.0).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+use std::io::{self, Read, Write};
+
+use crate::image::Image;
+use crate::ppm::invalid_data;
+use crate::Vec3;
+
+/*
+** Radiance RGBE (.hdr): each pixel is stored as three 8-bit mantissas sharing one 8-bit exponent,
+** which covers a huge range of linear radiance values in 4 bytes.
+** Format ref: https://paulbourke.net/dataformats/pic/
+*/
+pub fn write_hdr<W: Write>(image: &Image, mut out: W) -> io::Result<()> {
+    writeln!(out, "#?RADIANCE")?;
+    writeln!(out, "FORMAT=32-bit_rle_rgbe")?;
+    writeln!(out)?;
+    /* Rows go from the top (-Y) and columns from the left (+X) */
+    writeln!(out, "-Y {} +X {}", image.height(), image.width())?;
+
+    let mut scanline = Vec::with_capacity(image.width());
+    for j in 0..image.height() {
+        scanline.clear();
+        scanline.extend((0..image.width()).map(|i| to_rgbe(image.pixel(i, j))));
+        write_scanline(&scanline, &mut out)?;
+    }
+
+    out.flush()
+}
+
+/* Converts a linear color to the shared exponent representation. Negative values are stored as 0 */
+pub fn to_rgbe(color: Vec3) -> [u8; 4] {
+    let (r, g, b) = (color.x().max(0.0), color.y().max(0.0), color.z().max(0.0));
+    let brightest = r.max(g).max(b);
+
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    /* brightest = mantissa * 2^exponent, with mantissa in [0.5, 1) */
+    let exponent = brightest.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+
+    [(r * scale) as u8, (g * scale) as u8, (b * scale) as u8, (exponent + 128).clamp(0, 255) as u8]
+}
+
+/* Inverse of to_rgbe. The mantissas are offset by half a step, as they were truncated when encoded */
+pub fn from_rgbe(rgbe: [u8; 4]) -> Vec3 {
+    if rgbe[3] == 0 {
+        return Vec3(0.0, 0.0, 0.0);
+    }
+
+    let scale = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Vec3((rgbe[0] as f64 + 0.5) * scale, (rgbe[1] as f64 + 0.5) * scale, (rgbe[2] as f64 + 0.5) * scale)
+}
+
+/* Reads a Radiance file with the usual "-Y height +X width" orientation, flat or run length encoded */
+pub fn read_hdr<R: Read>(mut input: R) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut pos = 0;
+    let mut next_line = || -> io::Result<&str> {
+        let start = pos;
+        let end = data[start..].iter().position(|&byte| byte == b'\n').map(|len| start + len)
+            .ok_or_else(|| invalid_data("unexpected end of Radiance header".to_string()))?;
+        pos = end + 1;
+        std::str::from_utf8(&data[start..end]).map_err(|_| invalid_data("Radiance header is not valid text".to_string()))
+    };
+
+    let magic = next_line()?;
+    if !magic.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file".to_string()));
+    }
+
+    // Header variables end with an empty line.
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported Radiance pixel format {}", format)));
+            }
+        }
+    }
+
+    let resolution: Vec<&str> = next_line()?.split_whitespace().collect();
+    let (height, width): (usize, usize) = match resolution[..] {
+        ["-Y", height, "+X", width] => (
+            height.parse().map_err(|_| invalid_data(format!("invalid height {:?}", height)))?,
+            width.parse().map_err(|_| invalid_data(format!("invalid width {:?}", width)))?
+        ),
+        _ => return Err(invalid_data(format!("unsupported Radiance orientation {:?}", resolution.join(" "))))
+    };
+
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("invalid Radiance resolution {}x{}", width, height)));
+    }
+
+    /* Even with the longest runs, every scanline takes some bytes: check a corrupt resolution before allocating for it */
+    let min_scanline_len = if (8..=0x7fff).contains(&width) { 4 + 8 * width.div_ceil(127) } else { width.saturating_mul(4) };
+    if height.saturating_mul(min_scanline_len) > data.len() - pos {
+        return Err(invalid_data("Radiance file is shorter than its resolution says".to_string()));
+    }
+
+    let mut reader = ScanlineReader { data: &data, pos };
+    let mut image = Image::new(width, height);
+    for j in 0..height {
+        for (i, rgbe) in reader.scanline(width)?.into_iter().enumerate() {
+            image.set_pixel(i, j, from_rgbe(rgbe));
+        }
+    }
+
+    Ok(image)
+}
+
+struct ScanlineReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl ScanlineReader<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<&[u8]> {
+        let bytes = self.pos.checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| invalid_data("Radiance file is truncated".to_string()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn scanline(&mut self, width: usize) -> io::Result<Vec<[u8; 4]>> {
+        let is_rle = (8..=0x7fff).contains(&width) && self.data.get(self.pos..self.pos + 2) == Some(&[2, 2]);
+
+        if !is_rle {
+            return Ok(self.bytes(4 * width)?.chunks(4).map(|pixel| pixel.try_into().unwrap()).collect());
+        }
+
+        let marker = self.bytes(4)?;
+        if ((marker[2] as usize) << 8 | marker[3] as usize) != width {
+            return Err(invalid_data("Radiance scanline width mismatch".to_string()));
+        }
+
+        let mut scanline = vec![[0u8; 4]; width];
+        for c in 0..4 {
+            let mut i = 0;
+            while i < width {
+                let count = self.bytes(1)?[0] as usize;
+                let (run, len) = if count > 128 { (true, count - 128) } else { (false, count) };
+
+                if len == 0 || i + len > width {
+                    return Err(invalid_data("bad Radiance run length".to_string()));
+                }
+
+                if run {
+                    let value = self.bytes(1)?[0];
+                    for pixel in &mut scanline[i..i + len] {
+                        pixel[c] = value;
+                    }
+                }
+                else {
+                    for (pixel, &value) in scanline[i..i + len].iter_mut().zip(self.bytes(len)?) {
+                        pixel[c] = value;
+                    }
+                }
+                i += len;
+            }
+        }
+
+        Ok(scanline)
+    }
+}
+
+/*
+** Scanlines between 8 and 32767 pixels wide use the "new" run length encoding:
+** a (2, 2, width) marker, then each of the 4 components of the whole row encoded separately.
+** Other widths are written flat.
+*/
+fn write_scanline<W: Write>(scanline: &[[u8; 4]], out: &mut W) -> io::Result<()> {
+    let width = scanline.len();
+    if !(8..=0x7fff).contains(&width) {
+        for pixel in scanline {
+            out.write_all(pixel)?;
+        }
+        return Ok(());
+    }
+
+    out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+
+    let mut component = Vec::with_capacity(width);
+    for c in 0..4 {
+        component.clear();
+        component.extend(scanline.iter().map(|pixel| pixel[c]));
+        write_rle_component(&component, out)?;
+    }
+
+    Ok(())
+}
+
+/* Runs are written as (128 + count, value) and everything else as (count, values...). Both count at most 127 */
+fn write_rle_component<W: Write>(data: &[u8], out: &mut W) -> io::Result<()> {
+    const MIN_RUN: usize = 4;
+    const MAX_COUNT: usize = 127;
+
+    let run_length = |start: usize| data[start..].iter().take(MAX_COUNT).take_while(|&&value| value == data[start]).count();
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let run = run_length(pos);
+        if run >= MIN_RUN {
+            out.write_all(&[128 + run as u8, data[pos]])?;
+            pos += run;
+            continue;
+        }
+
+        // Gather literals until the next run worth encoding starts.
+        let start = pos;
+        while pos < data.len() && pos - start < MAX_COUNT && run_length(pos) < MIN_RUN {
+            pos += 1;
+        }
+        out.write_all(&[(pos - start) as u8])?;
+        out.write_all(&data[start..pos])?;
+    }
+
+    Ok(())
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgbe_conversion() {
+        assert_eq!(to_rgbe(Vec3(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Vec3(1.0, 0.5, 0.25)), [128, 64, 32, 129]);
+        assert_eq!(to_rgbe(Vec3(12.0, 0.0, 0.0)), [192, 0, 0, 132]);
+    }
+
+    #[test]
+    fn rle_runs_and_literals() {
+        let data = [7, 7, 7, 7, 7, 1, 2, 3];
+
+        let mut out = Vec::new();
+        write_rle_component(&data, &mut out).unwrap();
+
+        assert_eq!(out, vec![133, 7, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn oversized_resolution() {
+        /* Must fail on the size alone, without allocating 240 GB first */
+        for resolution in ["-Y 100000 +X 100000", "-Y 18446744073709551615 +X 18446744073709551615", "-Y 0 +X 5"] {
+            let data = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n\x02\x02\x01\x00");
+            assert!(read_hdr(data.as_bytes()).is_err(), "{resolution}");
+        }
+    }
+
+    #[test]
+    fn hdr_round_trip() {
+        /* One image wide enough to be run length encoded, one written flat */
+        for width in [10, 3] {
+            let mut image = Image::new(width, 2);
+            image.set_pixel(1, 0, Vec3(100.0, 0.5, 0.0));
+            image.set_pixel(width - 1, 1, Vec3(0.01, 0.02, 0.03));
+
+            let mut out = Vec::new();
+            write_hdr(&image, &mut out).unwrap();
+            let read = read_hdr(&out[..]).unwrap();
+
+            assert_eq!(read.width(), width);
+            assert_eq!(read.height(), 2);
+            for j in 0..2 {
+                for i in 0..width {
+                    let (expected, actual) = (image.pixel(i, j), read.pixel(i, j));
+                    /* RGBE keeps about 8 bits of precision relative to the brightest channel */
+                    let tolerance = 0.01 * expected.x().max(expected.y()).max(expected.z());
+                    assert_float_absolute_eq!(actual.x(), expected.x(), tolerance);
+                    assert_float_absolute_eq!(actual.y(), expected.y(), tolerance);
+                    assert_float_absolute_eq!(actual.z(), expected.z(), tolerance);
+                }
+            }
+        }
+    }
+}
@@ -1,30 +1,23 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::Vec3;
 use crate::Ray;
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::interval::Interval;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct HitRecord {
     pub point: Vec3,
     pub normal: Vec3,
     pub t: f64,
+    /* Surface coordinates of the hit point, used to look up textures */
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
-    pub material: Option<Rc<dyn Material>>
-}
-
-impl Default for HitRecord {
-    fn default() -> Self {
-        HitRecord {
-            point: Vec3::default(),
-            normal: Vec3::default(),
-            t: f64::default(),
-            front_face: bool::default(),
-            material: None
-        }
-    }
-    
+    pub material: Option<Arc<dyn Material>>,
+    /* Interpolated vertex color, for meshes that have one per vertex */
+    pub color: Option<Vec3>
 }
 
 impl HitRecord {
@@ -37,31 +30,41 @@ impl HitRecord {
         // Sets the hit record normal vector.
         // NOTE: the parameter `outward_normal` is assumed to have unit length.
         self.front_face = ray.direction().dot(outward_normal) < 0.0;
-        if let true = self.front_face {
+        if self.front_face {
             self.normal = outward_normal;
         }
         else {
-            self.normal = -1.0 * outward_normal;
+            self.normal = -outward_normal;
         }
     }
 }
 
-pub trait Hittable {
+/* Send + Sync so the world can be shared by the render threads */
+pub trait Hittable: Send + Sync {
     fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool;
+
+    fn bounding_box(&self) -> Aabb;
 }
 
 #[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Rc<dyn Hittable>>
+    objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb
 }
 
 impl HittableList {
-    pub fn add(&mut self, object: Rc<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.bbox = Aabb::enclosing(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::default();
+    }
+
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
     }
 }
 
@@ -81,4 +84,8 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 }
\ No newline at end of file
@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::slice::ChunksMut;
+
+use crate::Interval;
+use crate::Vec3;
+use crate::hdr;
+use crate::pfm;
+use crate::png::{self, BitDepth};
+use crate::ppm;
+
+/* The file formats an Image can be encoded to */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageFormat {
+    PpmAscii,
+    PpmBinary,
+    Png(BitDepth),
+    /* Float formats: they keep the unclamped linear radiance */
+    Pfm,
+    Hdr
+}
+
+impl ImageFormat {
+    /*
+    ** Picks the format from the file extension. PPM files are written as binary P6, 4 times smaller than ASCII P3.
+    ** PNG files default to 8 bits per channel: 16-bit PNG has no extension of its own,
+    ** ask for it with save_as and ImageFormat::Png(BitDepth::Sixteen)
+    */
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::PpmBinary),
+            "png" => Some(ImageFormat::Png(BitDepth::Eight)),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            _ => None
+        }
+    }
+
+    /* Parses a format name, as given on the command line */
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" | "ppm-binary" | "p6" => Some(ImageFormat::PpmBinary),
+            "ppm-ascii" | "p3" => Some(ImageFormat::PpmAscii),
+            "png" | "png8" => Some(ImageFormat::Png(BitDepth::Eight)),
+            "png16" => Some(ImageFormat::Png(BitDepth::Sixteen)),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            _ => None
+        }
+    }
+}
+
+/* 
+** In-memory framebuffer of linear radiance values, stored row by row from the top-left pixel.
+** Gamma correction and quantization only happen when an encoder writes the image out.
+*/
+#[derive(Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>
+}
+
+impl Image {
+    /* A black image */
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Vec3(0.0, 0.0, 0.0); width * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, i: usize, j: usize) -> Vec3 {
+        self.pixels[j * self.width + i]
+    }
+
+    pub fn set_pixel(&mut self, i: usize, j: usize, color: Vec3) {
+        self.pixels[j * self.width + i] = color;
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn write<W: Write>(&self, format: ImageFormat, out: W) -> io::Result<()> {
+        match format {
+            ImageFormat::PpmAscii => ppm::write_p3(self, out),
+            ImageFormat::PpmBinary => ppm::write_p6(self, out),
+            ImageFormat::Png(bit_depth) => png::write_png(self, bit_depth, out),
+            ImageFormat::Pfm => pfm::write_pfm(self, out),
+            ImageFormat::Hdr => hdr::write_hdr(self, out)
+        }
+    }
+
+    /* Writes the image to a file, in the format given by its extension */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display()))
+        })?;
+
+        self.save_as(path, format)
+    }
+
+    /* Writes the image to a file in the given format, whatever its extension */
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> io::Result<()> {
+        self.write(format, BufWriter::new(File::create(path)?))
+    }
+
+    /* Reads a PPM (P3 or P6), PFM or Radiance HDR file, telling them apart by their magic number */
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let mut data = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut data)?;
+
+        match data.get(..2) {
+            Some(b"P3") | Some(b"P6") => ppm::read_ppm(&data[..]),
+            Some(b"PF") | Some(b"Pf") => pfm::read_pfm(&data[..]),
+            Some(b"#?") => hdr::read_hdr(&data[..]),
+            _ => Err(ppm::invalid_data("unsupported image format, expected PPM, PFM or HDR".to_string()))
+        }
+    }
+
+    /* Rows are disjoint slices, so they can be filled in by different threads */
+    pub fn rows_mut(&mut self) -> ChunksMut<'_, Vec3> {
+        self.pixels.chunks_mut(self.width)
+    }
+}
+
+/* Transform from "linear space"  to "gamma space, a collor correction commonly expected when storing images
+** This will represent color intensity more accurately
+*/
+pub fn linear_to_gamma(linear_component: f64) -> f64 {
+    if linear_component > 0.0 { 
+        return linear_component.sqrt();
+    }
+
+    0.0
+}
+
+/* Inverse of linear_to_gamma */
+pub fn gamma_to_linear(gamma_component: f64) -> f64 {
+    gamma_component * gamma_component
+}
+
+/* Gamma correct a linear color and translate its [0,1] components to the integer range [0, max_value] */
+pub fn encode_color(pixel_color: Vec3, max_value: u32) -> [u32; 3] {
+    let intensity = Interval(0.0, 1.0);
+    let encode = |c: f64| {
+        let scaled = (max_value as f64 + 1.0) * intensity.clamp(linear_to_gamma(c));
+        (scaled as u32).min(max_value)
+    };
+
+    [encode(pixel_color.x()), encode(pixel_color.y()), encode(pixel_color.z())]
+}
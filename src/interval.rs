@@ -1,13 +1,17 @@
-use std::f64::{INFINITY, NEG_INFINITY};
-
 #[derive(Copy, Clone)]
 pub struct Interval(pub f64, pub f64);
 
-const EMPTY: Interval = Interval(INFINITY, NEG_INFINITY);
+const EMPTY: Interval = Interval(f64::INFINITY, f64::NEG_INFINITY);
 
-const UNIVERSE: Interval = Interval(NEG_INFINITY, INFINITY);
+#[allow(dead_code)]
+const UNIVERSE: Interval = Interval(f64::NEG_INFINITY, f64::INFINITY);
 
 impl Interval {
+    /* The tightest interval enclosing both input intervals */
+    pub fn enclosing(a: Interval, b: Interval) -> Interval {
+        Interval(a.0.min(b.0), a.1.max(b.1))
+    }
+
     pub fn min(self) -> f64 {
         self.0
     }
@@ -28,6 +32,12 @@ impl Interval {
         self.0 <= x && x <= self.1
     }
 
+    /* Pad the interval by delta / 2 on each side */
+    pub fn expand(self, delta: f64) -> Interval {
+        let padding = delta / 2.0;
+        Interval(self.0 - padding, self.1 + padding)
+    }
+
     /*  When doing antialiasing, ensure that the color components of the final result remain within the proper [0,1] bounds */
     pub fn clamp(self, x: f64) -> f64 {
         if x < self.0 { return self.0; }
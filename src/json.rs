@@ -0,0 +1,374 @@
+use std::fmt;
+
+/* A parsed JSON document. Object members keep their file order */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    /* Member of an object. None for other values, or if the key isn't there */
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(members) => Some(members),
+            _ => None
+        }
+    }
+
+    /* For error messages */
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "a boolean",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object"
+        }
+    }
+}
+
+/* Syntax error, with the 1-based line and column where it was found */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/* Parses a whole document. Anything but whitespace after the top-level value is an error */
+pub fn parse(source: &str) -> Result<Json, ParseError> {
+    let mut parser = Parser { source, pos: 0 };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < source.len() {
+        return Err(parser.error("unexpected characters after the end of the document"));
+    }
+
+    Ok(value)
+}
+
+/* Nesting deeper than this is certainly a broken file, and would otherwise overflow the stack */
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize // Byte offset of the next character
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Json, ParseError> {
+        self.nested_value(0)
+    }
+
+    fn nested_value(&mut self, depth: usize) -> Result<Json, ParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.literal(),
+            Some(c) => Err(self.error(&format!("unexpected character '{c}'"))),
+            None => Err(self.error("unexpected end of file"))
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, ParseError> {
+        self.expect('{')?;
+        let mut members: Vec<(String, Json)> = Vec::new();
+
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a member name in double quotes"));
+            }
+            let key_pos = self.pos;
+            let key = self.string()?;
+            if members.iter().any(|(name, _)| *name == key) {
+                self.pos = key_pos;
+                return Err(self.error(&format!("duplicate member \"{key}\"")));
+            }
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.nested_value(depth + 1)?;
+            members.push((key, value));
+
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(Json::Object(members));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, ParseError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.nested_value(depth + 1)?);
+
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(Json::Array(values));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut result = String::new();
+
+        loop {
+            let c = self.next().ok_or_else(|| self.error("unterminated string"))?;
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escape = self.next().ok_or_else(|| self.error("unterminated string"))?;
+                    match escape {
+                        '"' | '\\' | '/' => result.push(escape),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'u' => result.push(self.unicode_escape()?),
+                        _ => return Err(self.error(&format!("invalid escape '\\{escape}'")))
+                    }
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => result.push(c)
+            }
+        }
+    }
+
+    /* The 4 hex digits after \u. Characters outside the BMP come as a pair of surrogates */
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"));
+        }
+
+        if !(self.eat('\\') && self.eat('u')) {
+            return Err(self.error("unpaired surrogate in unicode escape"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate in unicode escape"));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16)).ok_or_else(|| self.error("expected 4 hex digits in unicode escape"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    /* -? int frac? exp?, where int has no leading zeros */
+    fn number(&mut self) -> Result<Json, ParseError> {
+        let start = self.pos;
+
+        self.eat('-');
+        if !self.eat('0') && self.digits() == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat('.') && self.digits() == 0 {
+            return Err(self.error("expected a digit after the decimal point"));
+        }
+        if self.eat('e') || self.eat('E') {
+            if !self.eat('+') {
+                self.eat('-');
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+
+        let text = &self.source[start..self.pos];
+        text.parse().map(Json::Number).map_err(|_| self.error(&format!("invalid number \"{text}\"")))
+    }
+
+    fn digits(&mut self) -> usize {
+        let mut count = 0;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.pos += 1;
+            count += 1;
+        }
+        count
+    }
+
+    fn literal(&mut self) -> Result<Json, ParseError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+
+        match &self.source[start..self.pos] {
+            "null" => Ok(Json::Null),
+            "true" => Ok(Json::Bool(true)),
+            "false" => Ok(Json::Bool(false)),
+            word => {
+                let message = format!("unknown literal \"{word}\"");
+                self.pos = start;
+                Err(self.error(&message))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /* Consumes the character if it's the next one */
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.eat(expected) {
+            return Ok(());
+        }
+
+        match self.peek() {
+            Some(c) => Err(self.error(&format!("expected '{expected}', found '{c}'"))),
+            None => Err(self.error(&format!("expected '{expected}', found the end of file")))
+        }
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        let before = &self.source[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+        ParseError { line, column, message: message.to_string() }
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        let document = parse(r#"
+            {
+                "name": "three \"spheres\"\n",
+                "count": 3,
+                "scale": -1.5e2,
+                "visible": true,
+                "parent": null,
+                "center": [0, 0.5, -1],
+                "escaped": "é😀",
+                "empty": {}
+            }
+        "#).unwrap();
+
+        assert_eq!(document.get("name").unwrap().as_str(), Some("three \"spheres\"\n"));
+        assert_eq!(document.get("count").unwrap().as_f64(), Some(3.0));
+        assert_eq!(document.get("scale").unwrap().as_f64(), Some(-150.0));
+        assert_eq!(document.get("visible"), Some(&Json::Bool(true)));
+        assert_eq!(document.get("parent"), Some(&Json::Null));
+        assert_eq!(document.get("center"), Some(&Json::Array(vec![Json::Number(0.0), Json::Number(0.5), Json::Number(-1.0)])));
+        assert_eq!(document.get("escaped").unwrap().as_str(), Some("é😀"));
+        assert_eq!(document.get("empty").unwrap().as_object().unwrap().len(), 0);
+        assert_eq!(document.get("missing"), None);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = parse("{\n  \"a\": 1,\n  \"b\": tru\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 8));
+        assert_eq!(error.to_string(), "line 3, column 8: unknown literal \"tru\"");
+
+        for source in ["", "[1, 2", "{\"a\" 1}", "[1,]", "01", "1.", "\"tab\there\"", "{\"a\": 1, \"a\": 2}", "[] []", "{1: 2}"] {
+            assert!(parse(source).is_err(), "{source} should not parse");
+        }
+
+        assert!(parse(&"[".repeat(1000)).is_err());
+    }
+}
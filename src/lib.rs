@@ -0,0 +1,67 @@
+/*
+** A path tracer following the "Ray Tracing in One Weekend" books, as a library.
+** Build a HittableList (by hand or from a JSON Scene), point a Camera at it and render it into an Image.
+** The most used types are re-exported here; the public modules hold the rest (file formats, geometry helpers, etc.).
+** Internal helpers (random numbers, Perlin noise, JSON, distributions) stay private; what users need from them is re-exported below.
+*/
+
+pub mod vec3;
+pub mod ray;
+pub mod hittable;
+pub mod sphere;
+pub mod interval;
+pub mod camera;
+mod random;
+pub mod material;
+pub mod aabb;
+pub mod bvh;
+pub mod image;
+mod ppm;
+mod png;
+mod pfm;
+mod hdr;
+pub mod texture;
+mod perlin;
+pub mod environment;
+mod distribution;
+pub mod quad;
+pub mod triangle;
+pub mod mesh;
+pub mod obj;
+pub mod ply;
+pub mod transform;
+pub mod matrix;
+pub mod quat;
+mod json;
+pub mod scene;
+pub mod sampler;
+
+pub use aabb::Aabb;
+pub use bvh::BvhNode;
+pub use camera::{Camera, CameraError};
+pub use environment::{Background, Environment, EnvironmentMap};
+pub use hittable::{HitRecord, Hittable, HittableList};
+pub use image::{Image, ImageFormat};
+pub use interval::Interval;
+pub use material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+pub use matrix::{Mat3, Mat4};
+pub use mesh::{Mesh, MeshData};
+pub use obj::load_obj;
+pub use perlin::Perlin;
+pub use ply::load_ply;
+pub use png::BitDepth;
+pub use quad::{make_box, Quad};
+pub use quat::Quat;
+pub use random::{seed, Rng};
+pub use ray::Ray;
+pub use sampler::{Sampler, SamplerType};
+pub use scene::{Scene, SceneError};
+pub use sphere::Sphere;
+pub use texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture, TurbulenceTexture, VertexColorTexture};
+pub use transform::{Instance, Transform};
+pub use triangle::Triangle;
+pub use vec3::Vec3;
+
+#[cfg(test)]
+#[macro_use]
+extern crate assert_float_eq;
@@ -1,90 +1,72 @@
-use std::f64::INFINITY;
-use std::{fs::File, rc::Rc};
-
-mod vec3;
-mod ray;
-mod hittable; 
-mod sphere;
-mod interval;
-mod camera;
-mod random;
-mod material;
-
-use camera::Camera;
-use hittable::{HitRecord, Hittable, HittableList};
-use interval::Interval;
-use material::{Dielectric, Lambertian, Metal};
-use sphere::Sphere;
-use vec3::Vec3;
-use ray::Ray;
+use std::process::ExitCode;
+use std::sync::Arc;
 
+mod cli;
+
+use cli::Command;
+use rust_ray_tracing::{BvhNode, Camera, Dielectric, HittableList, Lambertian, Metal, Scene, Sphere, Vec3};
+
+#[cfg(test)]
 #[macro_use]
 extern crate assert_float_eq;
 
-fn main() {
+/* The three spheres scene, rendered when no scene file is given */
+fn demo_scene() -> Scene {
     // World
     let mut world: HittableList = Default::default();
 
-    let material_ground = Rc::new(
-        Lambertian {
-            albedo: Vec3(0.8, 0.8, 0.0)
-        }
-    );
+    let material_ground = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.0)));
 
-    let material_center = Rc::new(
-        Lambertian {
-            albedo: Vec3(0.1, 0.2, 0.5)
-        }
-    );
+    let material_center = Arc::new(Lambertian::new(Vec3(0.1, 0.2, 0.5)));
 
-    let material_left = Rc::new(
+    let material_left = Arc::new(
         Dielectric {
             refraction_index: 1.5
         }
     );
 
-    let material_bubble = Rc::new(
+    let material_bubble = Arc::new(
         Dielectric {
             refraction_index: 1.00 / 1.50
         }
     );
 
-    let material_right = Rc::new(
+    let material_right = Arc::new(
         Metal {
             albedo: Vec3(0.8, 0.6, 0.2),
             fuzz: 1.0
         }
     );
 
-    world.add(Rc::new(
+    world.add(Arc::new(
         Sphere {
             center: Vec3(0.0, 0.0, -1.2),
             radius: 0.5,
             material: material_center
         }
     ));
-    world.add(Rc::new(
+    world.add(Arc::new(
         Sphere {
             center: Vec3(0.0, -100.5, -1.0),
             radius: 100.0,
             material: material_ground
         }
     ));
-    world.add(Rc::new(
+    world.add(Arc::new(
         Sphere {
             center: Vec3(-1.0, 0.0, -1.0),
             radius: 0.5,
             material: material_left
         }
     ));
-    world.add(Rc::new(
+    world.add(Arc::new(
         Sphere {
             center: Vec3(-1.0, 0.0, -1.0),
             radius: 0.4,
             material: material_bubble
         }
     ));
-    world.add(Rc::new(
+    world.add(Arc::new(
         Sphere {
             center: Vec3(1.0, 0.0, -1.0),
             radius: 0.5,
@@ -92,8 +74,6 @@ fn main() {
         }
     ));
 
-    let f = File::create("image.ppm").unwrap();
-
     let mut cam: Camera = Default::default();
 
     cam.aspect_ratio = 16.0/9.0;
@@ -101,5 +81,67 @@ fn main() {
     cam.samples_per_pixel = 100;
     cam.max_depth = 50;
 
-    cam.render(world, f);
-}
\ No newline at end of file
+    cam.vfov = 20.0;
+    cam.look_from = Vec3(-2.0, 2.0, 1.0);
+    cam.look_at = Vec3(0.0, 0.0, -1.0);
+    cam.vup = Vec3(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 10.0;
+    cam.focus_dist = 3.4;
+
+    Scene { camera: cam, world }
+}
+
+fn main() -> ExitCode {
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Command::Render(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    /* Seeding before loading the scene makes procedural textures (Perlin noise) reproducible too */
+    if let Some(seed) = options.seed {
+        rust_ray_tracing::seed(seed);
+    }
+
+    let scene = match &options.scene {
+        Some(path) => match Scene::load(path) {
+            Ok(scene) => scene,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => demo_scene()
+    };
+
+    if scene.world.objects().is_empty() {
+        eprintln!("error: the scene has no objects");
+        return ExitCode::FAILURE;
+    }
+
+    let mut cam = scene.camera;
+    options.apply(&mut cam);
+
+    /* Scene files are checked while loading, but not the built-in demo scene */
+    let image = match cam.render(BvhNode::new(scene.world)) {
+        Ok(image) => image,
+        Err(error) => {
+            eprintln!("error: camera: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(error) = image.save_as(&options.output, options.format) {
+        eprintln!("error: {}: {error}", options.output.display());
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
@@ -1,21 +1,51 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
 use crate::Ray;
 use crate::HitRecord;
 use crate::Vec3;
-use crate::random::random;
+use crate::sampler::{sample_uniform_sphere, Sampler};
+use crate::texture::{SolidColor, Texture};
+
+/* Send + Sync so materials can be shared by the render threads */
+pub trait Material: Send + Sync {
+    /* Every random decision comes from the sampler, as the next dimensions of the pixel sample */
+    fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> Option<(Ray, Vec3)>{
+        None
+    }
+
+    /* 
+    ** Density of scatter() choosing the direction of "scattered", for materials that scatter in all directions (diffuse).
+    ** Their attenuation must already include the cosine weighting of that density, so that another direction
+    ** can be used in its place when weighted by scattering_pdf / (density of the other sampling strategy).
+    ** Materials that scatter in one specific direction (mirrors, glass) keep the default 0.
+    */
+    fn scattering_pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
 
-pub trait Material {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)>{
-        return None;
+    /* Light given off by the surface itself. Most materials don't emit any */
+    fn emitted(&self, _u: f64, _v: f64, _point: Vec3) -> Vec3 {
+        Vec3(0.0, 0.0, 0.0)
     }
 }
 
 pub struct Lambertian {
-    pub albedo: Vec3
+    pub texture: Arc<dyn Texture>
+}
+
+impl Lambertian {
+    /* A Lambertian with the same albedo all over the surface */
+    pub fn new(albedo: Vec3) -> Lambertian {
+        Lambertian {
+            texture: Arc::new(SolidColor { albedo })
+        }
+    }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
-        let mut scatter_direction = hit_record.normal + Vec3::random_unit();
+    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Vec3)> {
+        let mut scatter_direction = hit_record.normal + sample_uniform_sphere(sampler.get_2d());
         // Catch degenerate scatter direction
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal;
@@ -25,10 +55,16 @@ impl Material for Lambertian {
             orig: hit_record.point,
             dir: scatter_direction
         };
-        let attenuation = self.albedo;
+        let attenuation = self.texture.value_at(hit_record);
 
         Some((scattered, attenuation))
     }
+
+    /* normal + a uniform unit vector is distributed like cos(theta) / pi over the hemisphere */
+    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = hit_record.normal.dot(scattered.direction().unit());
+        if cos_theta < 0.0 { 0.0 } else { cos_theta / PI }
+    }
 }
 
 pub struct Metal {
@@ -37,9 +73,9 @@ pub struct Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Vec3)> {
         let reflected_direction = ray_in.direction().reflect(&hit_record.normal);
-        let fuzz_direction = reflected_direction.unit() + (self.fuzz * Vec3::random_unit());
+        let fuzz_direction = reflected_direction.unit() + (self.fuzz * sample_uniform_sphere(sampler.get_2d()));
 
         if fuzz_direction.dot(hit_record.normal) > 0.0 {
             let reflected = Ray {
@@ -52,7 +88,7 @@ impl Material for Metal {
         }
         /* If the fuzz calculation goes inwards the material, we absorb it */
         else {
-            return None;
+            None
         }
     }
 }
@@ -68,12 +104,12 @@ impl Dielectric {
     */
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
         let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powi(2);
-        return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Vec3)> {
         let mut refraction_i = self.refraction_index;
 
         if hit_record.front_face {
@@ -87,14 +123,13 @@ impl Material for Dielectric {
         ** 1. The ray is coming from a material with a higher refraction index than the current material and the angle is too steep
         ** 2. Disregarding the material, reflectance is higher than a random number
         */
-        let direction;
-        if refraction_i * sin_in > 1.0 || Dielectric::reflectance(cos_in, refraction_i) > random() {
+        let direction = if refraction_i * sin_in > 1.0 || Dielectric::reflectance(cos_in, refraction_i) > sampler.get_1d() {
             /* reflect is borrowing the normal, while the refract isn't. It would be nice to make them the same way */
-            direction = ray_in.direction().unit().reflect(&hit_record.normal);
+            ray_in.direction().unit().reflect(&hit_record.normal)
         }
         else {
-            direction = ray_in.direction().unit().refract(hit_record.normal, refraction_i);
-        }
+            ray_in.direction().unit().refract(hit_record.normal, refraction_i)
+        };
 
         let scattered = Ray {
             orig: hit_record.point,
@@ -105,4 +140,61 @@ impl Material for Dielectric {
 
         Some((scattered, attenuation))
     }
-}
\ No newline at end of file
+}
+
+/* Emits light and doesn't scatter any: an area light takes the shape of whatever object uses this material */
+pub struct DiffuseLight {
+    pub texture: Arc<dyn Texture>
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> DiffuseLight {
+        DiffuseLight {
+            texture: Arc::new(SolidColor { albedo: emit })
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, point: Vec3) -> Vec3 {
+        self.texture.value(u, v, point)
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::SamplerType;
+    use crate::texture::CheckerTexture;
+
+    #[test]
+    fn lights_emit_and_absorb() {
+        let light = DiffuseLight { texture: Arc::new(CheckerTexture::from_colors(1.0, Vec3(4.0, 4.0, 4.0), Vec3(1.0, 0.5, 0.0))) };
+        let ray = Ray { orig: Vec3(0.0, 0.0, 1.0), dir: Vec3(0.0, 0.0, -1.0) };
+        let hit_record = HitRecord { normal: Vec3(0.0, 0.0, 1.0), front_face: true, ..Default::default() };
+        let mut sampler = SamplerType::Independent.create(1, 0);
+
+        assert!(light.scatter(&ray, &hit_record, sampler.as_mut()).is_none());
+        /* The texture decides the color, at every point */
+        assert_eq!(light.emitted(0.0, 0.0, Vec3(0.5, 0.5, 0.5)), Vec3(4.0, 4.0, 4.0));
+        assert_eq!(light.emitted(0.0, 0.0, Vec3(1.5, 0.5, 0.5)), Vec3(1.0, 0.5, 0.0));
+        assert_eq!(DiffuseLight::new(Vec3(2.0, 1.0, 0.5)).emitted(0.3, 0.7, Vec3(0.0, 0.0, 0.0)), Vec3(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn other_materials_emit_black() {
+        let materials: [&dyn Material; 3] = [
+            &Lambertian::new(Vec3(0.9, 0.9, 0.9)),
+            &Metal { albedo: Vec3(0.9, 0.9, 0.9), fuzz: 0.0 },
+            &Dielectric { refraction_index: 1.5 }
+        ];
+
+        for material in materials {
+            assert_eq!(material.emitted(0.5, 0.5, Vec3(1.0, 2.0, 3.0)), Vec3(0.0, 0.0, 0.0));
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::ops::Mul;
+
+use crate::Vec3;
+
+/* 3x3 matrix, stored row by row. Multiplying it by a Vec3 treats the vector as a column */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3(pub [[f64; 3]; 3]);
+
+/* 4x4 matrix, stored row by row. Used for affine and projective transforms in homogeneous coordinates */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4(pub [[f64; 4]; 4]);
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat3 {
+    pub fn identity() -> Mat3 {
+        Mat3([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    pub fn from_rows(r0: Vec3, r1: Vec3, r2: Vec3) -> Mat3 {
+        Mat3([[r0.x(), r0.y(), r0.z()], [r1.x(), r1.y(), r1.z()], [r2.x(), r2.y(), r2.z()]])
+    }
+
+    pub fn from_columns(c0: Vec3, c1: Vec3, c2: Vec3) -> Mat3 {
+        Self::from_rows(c0, c1, c2).transpose()
+    }
+
+    pub fn row(&self, i: usize) -> Vec3 {
+        Vec3(self.0[i][0], self.0[i][1], self.0[i][2])
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        let m = &self.0;
+        Mat3(std::array::from_fn(|i| std::array::from_fn(|j| m[j][i])))
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.row(0).dot(self.row(1).cross(self.row(2)))
+    }
+
+    /*
+    ** The columns of the inverse are the cross products of the rows, divided by the determinant.
+    ** Returns None for singular matrices.
+    */
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let (r0, r1, r2) = (self.row(0), self.row(1), self.row(2));
+        let inv_det = 1.0 / det;
+        Some(Mat3::from_columns(inv_det * r1.cross(r2), inv_det * r2.cross(r0), inv_det * r0.cross(r1)))
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3(std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| self.0[i][k] * rhs.0[k][j]).sum())))
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3(self.row(0).dot(rhs), self.row(1).dot(rhs), self.row(2).dot(rhs))
+    }
+}
+
+impl Mat4 {
+    pub fn identity() -> Mat4 {
+        Mat4([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    /* Affine matrix with the given linear part and translation */
+    pub fn from_mat3(linear: Mat3, translation: Vec3) -> Mat4 {
+        let l = &linear.0;
+        Mat4([
+            [l[0][0], l[0][1], l[0][2], translation.x()],
+            [l[1][0], l[1][1], l[1][2], translation.y()],
+            [l[2][0], l[2][1], l[2][2], translation.z()],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    /* Upper left 3x3 block: the linear part of an affine transform */
+    pub fn to_mat3(self) -> Mat3 {
+        Mat3(std::array::from_fn(|i| std::array::from_fn(|j| self.0[i][j])))
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Self::from_mat3(Mat3::identity(), offset)
+    }
+
+    pub fn scaling(factors: Vec3) -> Mat4 {
+        Self::from_mat3(Mat3([[factors.x(), 0.0, 0.0], [0.0, factors.y(), 0.0], [0.0, 0.0, factors.z()]]), Vec3::default())
+    }
+
+    /* Rotation of "degrees" around an axis through the origin, counterclockwise when looking down the axis (Rodrigues' formula) */
+    pub fn rotation(axis: Vec3, degrees: f64) -> Mat4 {
+        let a = axis.unit();
+        let (sin_theta, cos_theta) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        Self::from_mat3(Mat3([
+            [x * x + (1.0 - x * x) * cos_theta, x * y * (1.0 - cos_theta) - z * sin_theta, x * z * (1.0 - cos_theta) + y * sin_theta],
+            [x * y * (1.0 - cos_theta) + z * sin_theta, y * y + (1.0 - y * y) * cos_theta, y * z * (1.0 - cos_theta) - x * sin_theta],
+            [x * z * (1.0 - cos_theta) - y * sin_theta, y * z * (1.0 - cos_theta) + x * sin_theta, z * z + (1.0 - z * z) * cos_theta]
+        ]), Vec3::default())
+    }
+
+    /*
+    ** View matrix: moves the world so the eye is at the origin looking down -Z, with "up" along +Y.
+    ** Uses the same u, v, w basis as the Camera.
+    */
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let w = (eye - target).unit();
+        let u = up.cross(w).unit();
+        let v = w.cross(u);
+
+        let rotation = Mat3::from_rows(u, v, w);
+        Self::from_mat3(rotation, -(rotation * eye))
+    }
+
+    /*
+    ** Perspective projection of camera space (looking down -Z) to clip space, as in OpenGL:
+    ** after dividing by w, the visible volume is [-1, 1] on every axis, with the near plane at z = -1.
+    */
+    pub fn perspective(vfov: f64, aspect_ratio: f64, near: f64, far: f64) -> Mat4 {
+        let f = 1.0 / (vfov.to_radians() / 2.0).tan();
+        Mat4([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / (near - far), 2.0 * far * near / (near - far)],
+            [0.0, 0.0, -1.0, 0.0]
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let m = &self.0;
+        Mat4(std::array::from_fn(|i| std::array::from_fn(|j| m[j][i])))
+    }
+
+    /* Laplace expansion along the first row */
+    pub fn determinant(&self) -> f64 {
+        (0..4).map(|j| {
+            let sign = if j % 2 == 0 { 1.0 } else { -1.0 };
+            sign * self.0[0][j] * self.minor(0, j).determinant()
+        }).sum()
+    }
+
+    /* Gauss-Jordan elimination with partial pivoting. Returns None for singular matrices */
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.0;
+        let mut inv = Mat4::identity().0;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&r1, &r2| a[r1][col].abs().total_cmp(&a[r2][col].abs())).unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+
+        Some(Mat4(inv))
+    }
+
+    /* Points are affected by the translation part (w = 1). Projective matrices also divide by the resulting w */
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let [x, y, z, w] = self.apply([p.x(), p.y(), p.z(), 1.0]);
+        if w == 1.0 { Vec3(x, y, z) } else { Vec3(x, y, z) / w }
+    }
+
+    /* Vectors (directions) aren't affected by the translation part (w = 0) */
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let [x, y, z, _] = self.apply([v.x(), v.y(), v.z(), 0.0]);
+        Vec3(x, y, z)
+    }
+
+    /*
+    ** Normals must stay perpendicular to the transformed surface, so they go through the inverse transpose.
+    ** Call this on the inverse of the matrix that transforms the points, which is usually at hand already.
+    */
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        self.to_mat3().transpose() * n
+    }
+
+    fn apply(&self, v: [f64; 4]) -> [f64; 4] {
+        std::array::from_fn(|i| (0..4).map(|k| self.0[i][k] * v[k]).sum())
+    }
+
+    /* The 3x3 matrix left after removing a row and a column */
+    fn minor(&self, row: usize, col: usize) -> Mat3 {
+        let rows: Vec<usize> = (0..4).filter(|&r| r != row).collect();
+        let cols: Vec<usize> = (0..4).filter(|&c| c != col).collect();
+        Mat3(std::array::from_fn(|i| std::array::from_fn(|j| self.0[rows[i]][cols[j]])))
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        Mat4(std::array::from_fn(|i| std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())))
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert_float_absolute_eq!(a.x(), b.x());
+        assert_float_absolute_eq!(a.y(), b.y());
+        assert_float_absolute_eq!(a.z(), b.z());
+    }
+
+    fn assert_mat4_eq(a: Mat4, b: Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert_float_absolute_eq!(a.0[i][j], b.0[i][j]);
+            }
+        }
+    }
+
+    #[test]
+    fn mat3_inverse_and_determinant() {
+        let m = Mat3([[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]]);
+
+        assert_float_absolute_eq!(m.determinant(), 25.0);
+
+        let product = m * m.inverse().unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_float_absolute_eq!(product.0[i][j], if i == j { 1.0 } else { 0.0 });
+            }
+        }
+
+        assert!(Mat3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 0.0]]).inverse().is_none());
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn mat4_inverse_and_determinant() {
+        let m = Mat4::translation(Vec3(1.0, -2.0, 3.0)) * Mat4::rotation(Vec3(1.0, 1.0, 0.0), 30.0) * Mat4::scaling(Vec3(2.0, 3.0, 0.5));
+
+        assert_float_absolute_eq!(m.determinant(), 3.0);
+        assert_mat4_eq(m * m.inverse().unwrap(), Mat4::identity());
+        assert_mat4_eq(m.inverse().unwrap() * m, Mat4::identity());
+        assert!(Mat4::scaling(Vec3(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn points_vectors_and_normals() {
+        let m = Mat4::translation(Vec3(0.0, 0.0, -5.0)) * Mat4::rotation(Vec3(0.0, 1.0, 0.0), 90.0) * Mat4::scaling(Vec3(2.0, 1.0, 1.0));
+
+        assert_vec_eq(m.transform_point(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, -7.0));
+        assert_vec_eq(m.transform_vector(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, -2.0));
+
+        let stretch = Mat4::scaling(Vec3(2.0, 1.0, 1.0));
+        assert_vec_eq(stretch.inverse().unwrap().transform_normal(Vec3(1.0, 1.0, 0.0)), Vec3(0.5, 1.0, 0.0));
+    }
+
+    #[test]
+    fn look_at_and_perspective() {
+        let view = Mat4::look_at(Vec3(0.0, 0.0, 5.0), Vec3(0.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+        assert_vec_eq(view.transform_point(Vec3(0.0, 0.0, 0.0)), Vec3(0.0, 0.0, -5.0));
+        assert_vec_eq(view.transform_point(Vec3(1.0, 2.0, 5.0)), Vec3(1.0, 2.0, 0.0));
+
+        /* The near and far planes map to -1 and 1, the top of the field of view to y = 1 */
+        let projection = Mat4::perspective(90.0, 2.0, 1.0, 10.0);
+        assert_float_absolute_eq!(projection.transform_point(Vec3(0.0, 0.0, -1.0)).z(), -1.0);
+        assert_float_absolute_eq!(projection.transform_point(Vec3(0.0, 0.0, -10.0)).z(), 1.0);
+        assert_vec_eq(projection.transform_point(Vec3(4.0, 2.0, -2.0)), Vec3(1.0, 1.0, projection.transform_point(Vec3(0.0, 0.0, -2.0)).z()));
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::triangle::{intersect, set_hit_record, triangle_bounding_box};
+use crate::Vec3;
+use crate::Ray;
+
+/* One triangle of a mesh, as indices into the shared vertex buffers */
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    pub material: usize, // Index in MeshData::materials
+    pub group: usize // Index in MeshData::groups
+}
+
+/* Vertex buffers shared by all the triangles of a mesh, so vertices aren't duplicated for every face that uses them */
+#[derive(Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    /* Either empty or one color per position */
+    pub colors: Vec<Vec3>,
+    pub faces: Vec<MeshFace>,
+    pub materials: Vec<Arc<dyn Material>>,
+    pub groups: Vec<String>
+}
+
+/* A triangle mesh, with its own BVH over the faces */
+pub struct Mesh {
+    data: Arc<MeshData>,
+    bvh: BvhNode
+}
+
+impl Mesh {
+    /* A mesh without faces is valid, and never hit */
+    pub fn new(data: MeshData) -> Mesh {
+        let data = Arc::new(data);
+        let triangles: Vec<Arc<dyn Hittable>> = (0..data.faces.len())
+            .map(|face| Arc::new(MeshTriangle { data: data.clone(), face }) as Arc<dyn Hittable>)
+            .collect();
+
+        Mesh {
+            bvh: BvhNode::from_objects(triangles),
+            data
+        }
+    }
+
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        self.bvh.hit(ray, ray_t, hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
+
+struct MeshTriangle {
+    data: Arc<MeshData>,
+    face: usize
+}
+
+impl MeshTriangle {
+    fn positions(&self) -> [Vec3; 3] {
+        self.data.faces[self.face].positions.map(|index| self.data.positions[index])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        let positions = self.positions();
+        let Some((t, b1, b2)) = intersect(ray, ray_t, positions) else {
+            return false;
+        };
+
+        let face = &self.data.faces[self.face];
+        let normals = face.normals.map(|indices| indices.map(|index| self.data.normals[index]));
+        let uvs = face.uvs.map(|indices| indices.map(|index| self.data.uvs[index]));
+
+        set_hit_record(hit_record, ray, t, (b1, b2), positions, normals, uvs);
+        hit_record.material = Some(self.data.materials[face.material].clone());
+        hit_record.color = if self.data.colors.is_empty() {
+            None
+        }
+        else {
+            let c = face.positions.map(|index| self.data.colors[index]);
+            Some((1.0 - b1 - b2) * c[0] + b1 * c[1] + b2 * c[2])
+        };
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        triangle_bounding_box(self.positions())
+    }
+}
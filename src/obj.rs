@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::mesh::{Mesh, MeshData, MeshFace};
+use crate::ppm::invalid_data;
+use crate::texture::ImageTexture;
+use crate::Vec3;
+
+/*
+** Wavefront OBJ loader. Supports positions (v), texture coordinates (vt), normals (vn), faces (f) with any number of
+** vertices, groups (g) and materials (mtllib/usemtl). Faces that use no material get default_material.
+** Format ref: https://paulbourke.net/dataformats/obj/
+*/
+pub fn load_obj<P: AsRef<Path>>(path: P, default_material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    Ok(Mesh::new(parse_obj(&source, base_dir, default_material)?))
+}
+
+/* mtllib files and texture maps are looked up relative to base_dir */
+pub fn parse_obj(source: &str, base_dir: &Path, default_material: Arc<dyn Material>) -> io::Result<MeshData> {
+    let mut data = MeshData {
+        materials: vec![default_material],
+        groups: vec!["default".to_string()],
+        ..Default::default()
+    };
+
+    let mut library: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    /* Material names already added to data.materials, with their index */
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut current_material = 0;
+    let mut current_group = 0;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| invalid_data(format!("line {}: {}", line_index + 1, message));
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => data.positions.push(parse_vec3(&args).map_err(error)?),
+            "vn" => data.normals.push(parse_vec3(&args).map_err(error)?),
+            "vt" => {
+                let u = parse_float(args.first().copied()).map_err(error)?;
+                /* v is optional, for 1D textures */
+                let v = if args.len() > 1 { parse_float(Some(args[1])).map_err(error)? } else { 0.0 };
+                data.uvs.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(format!("face needs at least 3 vertices, found {}", args.len())));
+                }
+
+                let vertices = args.iter()
+                    .map(|vertex| parse_face_vertex(vertex, &data))
+                    .collect::<Result<Vec<_>, String>>()
+                    .map_err(error)?;
+
+                /* Triangulate the polygon as a fan around its first vertex (assumes convex n-gons) */
+                for k in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[k], vertices[k + 1]];
+                    data.faces.push(MeshFace {
+                        positions: corners.map(|(position, _, _)| position),
+                        uvs: all_some(corners.map(|(_, uv, _)| uv)),
+                        normals: all_some(corners.map(|(_, _, normal)| normal)),
+                        material: current_material,
+                        group: current_group
+                    });
+                }
+            }
+            "g" | "o" => {
+                let name = if args.is_empty() { "default".to_string() } else { args.join(" ") };
+                current_group = match data.groups.iter().position(|group| *group == name) {
+                    Some(index) => index,
+                    None => {
+                        data.groups.push(name);
+                        data.groups.len() - 1
+                    }
+                };
+            }
+            "mtllib" => {
+                for file in &args {
+                    let mtl_path = base_dir.join(file);
+                    let mtl_source = fs::read_to_string(&mtl_path)
+                        .map_err(|e| error(format!("cannot read material library {}: {}", mtl_path.display(), e)))?;
+                    let mtl_materials = parse_mtl(&mtl_source, base_dir)
+                        .map_err(|e| error(format!("in material library {}: {}", mtl_path.display(), e)))?;
+                    library.extend(mtl_materials);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                current_material = match material_indices.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let material = library.get(&name).ok_or_else(|| error(format!("unknown material {:?}", name)))?;
+                        data.materials.push(material.clone());
+                        material_indices.insert(name, data.materials.len() - 1);
+                        data.materials.len() - 1
+                    }
+                };
+            }
+            // Smoothing groups and other statements don't affect what we render.
+            _ => {}
+        }
+    }
+
+    if data.faces.is_empty() {
+        return Err(invalid_data("OBJ file has no faces".to_string()));
+    }
+
+    Ok(data)
+}
+
+/*
+** Maps MTL definitions onto our materials:
+**  - emission (Ke) makes a DiffuseLight
+**  - transparency (d < 1, Tr > 0, or illum 4, 6, 7, 9) makes a Dielectric with index of refraction Ni,
+**    or 1.5 (glass) when Ni is missing or not above 1, as an index of 1 would make it invisible
+**  - mirror reflection (illum 3, 5, 8) or a specular color without a diffuse one makes a Metal,
+**    whose fuzz comes from the specular exponent Ns (higher is shinier)
+**  - everything else is Lambertian, textured by map_Kd when it is present. Maps we can't read (e.g. PNG or JPEG)
+**    fall back to the Kd color, with a warning
+*/
+pub fn parse_mtl(source: &str, base_dir: &Path) -> io::Result<HashMap<String, Arc<dyn Material>>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlDefinition)> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| invalid_data(format!("line {}: {}", line_index + 1, message));
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, definition)) = current.take() {
+                materials.insert(name, definition.to_material());
+            }
+            current = Some((args.join(" "), MtlDefinition::default()));
+            continue;
+        }
+
+        let Some((_, definition)) = current.as_mut() else {
+            return Err(error(format!("{} before any newmtl", keyword)));
+        };
+
+        match keyword {
+            "Kd" => definition.kd = parse_vec3(&args).map_err(error)?,
+            "Ks" => definition.ks = parse_vec3(&args).map_err(error)?,
+            "Ke" => definition.ke = parse_vec3(&args).map_err(error)?,
+            "Ns" => definition.ns = parse_float(args.first().copied()).map_err(error)?,
+            "Ni" => definition.ni = parse_float(args.first().copied()).map_err(error)?,
+            "d" => definition.dissolve = parse_float(args.first().copied()).map_err(error)?,
+            "Tr" => definition.dissolve = 1.0 - parse_float(args.first().copied()).map_err(error)?,
+            "illum" => definition.illum = parse_float(args.first().copied()).map_err(error)? as u32,
+            /* Options such as "-s 1 1 1" come before the file name, which is the last argument */
+            "map_Kd" => {
+                let file = args.last().ok_or_else(|| error("map_Kd without a file".to_string()))?;
+                definition.map_kd = Some(base_dir.join(file).to_string_lossy().into_owned());
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, definition)) = current {
+        materials.insert(name, definition.to_material());
+    }
+
+    Ok(materials)
+}
+
+struct MtlDefinition {
+    kd: Vec3,
+    ks: Vec3,
+    ke: Vec3,
+    ns: f64,
+    ni: f64,
+    dissolve: f64,
+    illum: u32,
+    map_kd: Option<String>
+}
+
+impl Default for MtlDefinition {
+    fn default() -> Self {
+        MtlDefinition {
+            kd: Vec3(0.8, 0.8, 0.8),
+            ks: Vec3(0.0, 0.0, 0.0),
+            ke: Vec3(0.0, 0.0, 0.0),
+            ns: 0.0,
+            ni: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            map_kd: None
+        }
+    }
+}
+
+impl MtlDefinition {
+    fn to_material(&self) -> Arc<dyn Material> {
+        if !self.ke.near_zero() {
+            return Arc::new(DiffuseLight::new(self.ke));
+        }
+
+        if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            let refraction_index = if self.ni > 1.0 { self.ni } else { 1.5 };
+            return Arc::new(Dielectric { refraction_index });
+        }
+
+        let is_mirror = matches!(self.illum, 3 | 5 | 8);
+        if (is_mirror || self.kd.near_zero()) && !self.ks.near_zero() {
+            /* Roughness of a Phong lobe with exponent Ns */
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt().min(1.0);
+            return Arc::new(Metal { albedo: self.ks, fuzz });
+        }
+
+        if let Some(file) = &self.map_kd {
+            match ImageTexture::load(file) {
+                Ok(texture) => return Arc::new(Lambertian { texture: Arc::new(texture) }),
+                Err(error) => eprintln!("warning: {}: {}, using the Kd color instead", file, error)
+            }
+        }
+        Arc::new(Lambertian::new(self.kd))
+    }
+}
+
+fn parse_float(token: Option<&str>) -> Result<f64, String> {
+    let token = token.ok_or_else(|| "missing number".to_string())?;
+    token.parse().map_err(|_| format!("expected a number, found {:?}", token))
+}
+
+fn parse_vec3(args: &[&str]) -> Result<Vec3, String> {
+    Ok(Vec3(parse_float(args.first().copied())?, parse_float(args.get(1).copied())?, parse_float(args.get(2).copied())?))
+}
+
+/* "v", "v/vt", "v//vn" or "v/vt/vn", with 1-based indices, or negative ones counting back from the last vertex */
+fn parse_face_vertex(vertex: &str, data: &MeshData) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = vertex.split('/');
+
+    let resolve = |token: Option<&str>, count: usize, what: &str| -> Result<Option<usize>, String> {
+        let Some(token) = token.filter(|token| !token.is_empty()) else { return Ok(None) };
+        let index: i64 = token.parse().map_err(|_| format!("invalid {} index {:?}", what, token))?;
+
+        let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(format!("{} index {} out of range (there are {})", what, index, count));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = resolve(parts.next(), data.positions.len(), "vertex")?.ok_or_else(|| format!("face vertex {:?} has no position", vertex))?;
+    let uv = resolve(parts.next(), data.uvs.len(), "texture coordinate")?;
+    let normal = resolve(parts.next(), data.normals.len(), "normal")?;
+
+    Ok((position, uv, normal))
+}
+
+/* Per-vertex attributes are only used when every corner of the face has them */
+fn all_some(values: [Option<usize>; 3]) -> Option<[usize; 3]> {
+    Some([values[0]?, values[1]?, values[2]?])
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::image::Image;
+    use crate::sampler::SamplerType;
+    use crate::Ray;
+
+    fn default_material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn quad_is_triangulated() {
+        let source = "
+            # A unit square in two groups
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vt 0 1
+            vn 0 0 1
+            g front
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            g back
+            f -1//1 -2//1 -3//1
+        ";
+
+        let data = parse_obj(source, Path::new("."), default_material()).unwrap();
+
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.faces.len(), 3);
+        assert_eq!(data.faces[0], MeshFace { positions: [0, 1, 2], uvs: Some([0, 1, 2]), normals: Some([0, 0, 0]), material: 0, group: 1 });
+        assert_eq!(data.faces[1].positions, [0, 2, 3]);
+        assert_eq!(data.faces[2].positions, [3, 2, 1]);
+        assert_eq!(data.faces[2].uvs, None);
+        assert_eq!(data.groups[data.faces[2].group], "back");
+    }
+
+    #[test]
+    fn invalid_files() {
+        let out_of_range = parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n", Path::new("."), default_material());
+        assert!(out_of_range.err().unwrap().to_string().contains("line 3"));
+
+        assert!(parse_obj("v 0 0\n", Path::new("."), default_material()).is_err());
+        assert!(parse_obj("v 0 0 0\n", Path::new("."), default_material()).is_err());
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n", Path::new("."), default_material()).is_err());
+    }
+
+    #[test]
+    fn materials_from_mtl() {
+        let dir = std::env::temp_dir().join(format!("obj-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("scene.mtl"), "
+            newmtl red
+            Kd 0.8 0.1 0.1
+            newmtl glass
+            Ni 1.5
+            d 0.1
+            newmtl lamp
+            Ke 4 4 4
+        ").unwrap();
+        let source = "
+            mtllib scene.mtl
+            v 0 0 -1
+            v 1 0 -1
+            v 0 1 -1
+            f 1 2 3
+            usemtl red
+            f 1 2 3
+            usemtl lamp
+            f 1 2 3
+            usemtl red
+            f 3 2 1
+        ";
+
+        let data = parse_obj(source, &dir, default_material()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        /* The default material, then each used material once */
+        assert_eq!(data.materials.len(), 3);
+        assert_eq!(data.faces.iter().map(|face| face.material).collect::<Vec<_>>(), vec![0, 1, 2, 1]);
+
+        let mesh = Mesh::new(data);
+        let lamp = &mesh.data().materials[2];
+        assert_eq!(lamp.emitted(0.0, 0.0, Vec3::default()), Vec3(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn mtl_fallbacks() {
+        let dir = std::env::temp_dir().join(format!("mtl-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Image::new(2, 2).save(dir.join("wood.png")).unwrap();
+
+        let materials = parse_mtl("
+            newmtl wood
+            Kd 0.2 0.4 0.6
+            map_Kd -s 1 1 1 wood.png
+            newmtl veil
+            d 0.5
+        ", &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let ray = Ray { orig: Vec3(0.0, 0.0, 1.0), dir: Vec3(1.0, 0.0, -1.0) };
+        let hit_record = HitRecord { normal: Vec3(0.0, 0.0, 1.0), front_face: true, ..Default::default() };
+        let mut sampler = SamplerType::Independent.create(16, 0);
+
+        /* We can't read PNG textures, so the wood gets its Kd color */
+        let (_, attenuation) = materials["wood"].scatter(&ray, &hit_record, sampler.as_mut()).unwrap();
+        assert_eq!(attenuation, Vec3(0.2, 0.4, 0.6));
+
+        /* Without Ni the veil is glass, which bends or reflects every ray. An index of 1 would let them through */
+        for sample in 0..16 {
+            sampler.start_pixel_sample((0, 0), sample);
+            let (scattered, _) = materials["veil"].scatter(&ray, &hit_record, sampler.as_mut()).unwrap();
+            assert!(scattered.direction().unit().cross(ray.direction().unit()).length() > 0.1);
+        }
+    }
+}
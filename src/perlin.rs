@@ -0,0 +1,151 @@
+use crate::random::{self, Rng};
+use crate::Vec3;
+
+const POINT_COUNT: usize = 256;
+
+/*
+** Perlin (gradient) noise: random unit vectors sit on the integer lattice, and the noise at a point
+** blends the dot products of the 8 surrounding lattice gradients with the offsets to that point.
+** Ref: https://raytracing.github.io/books/RayTracingTheNextWeek.html#perlinnoise
+*/
+pub struct Perlin {
+    randvec: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT]
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Perlin {
+    /* Draws its tables from the thread's generator (see random::seed) */
+    pub fn new() -> Perlin {
+        Self::with_rng(&mut random::fork())
+    }
+
+    pub fn with_rng(rng: &mut Rng) -> Perlin {
+        Perlin {
+            randvec: std::array::from_fn(|_| Vec3::random_unit(rng)),
+            perm_x: Self::generate_perm(rng),
+            perm_y: Self::generate_perm(rng),
+            perm_z: Self::generate_perm(rng)
+        }
+    }
+
+    /* Result is in [-1, 1], and 0 at every lattice point */
+    pub fn noise(&self, point: Vec3) -> f64 {
+        let u = point.x() - point.x().floor();
+        let v = point.y() - point.y().floor();
+        let w = point.z() - point.z().floor();
+
+        let i = point.x().floor() as i64;
+        let j = point.y().floor() as i64;
+        let k = point.z().floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, gradient) in row.iter_mut().enumerate() {
+                    *gradient = self.randvec[
+                        self.perm_x[((i + di as i64) & 255) as usize] ^
+                        self.perm_y[((j + dj as i64) & 255) as usize] ^
+                        self.perm_z[((k + dk as i64) & 255) as usize]
+                    ];
+                }
+            }
+        }
+
+        Self::perlin_interp(&c, u, v, w)
+    }
+
+    /* Sum of noise at increasing frequencies and decreasing amplitudes */
+    pub fn turb(&self, point: Vec3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_point = point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_point);
+            weight *= 0.5;
+            temp_point = 2.0 * temp_point;
+        }
+
+        accum.abs()
+    }
+
+    /* Random permutation of 0..POINT_COUNT (Fisher–Yates shuffle) */
+    fn generate_perm(rng: &mut Rng) -> [usize; POINT_COUNT] {
+        let mut p: [usize; POINT_COUNT] = std::array::from_fn(|i| i);
+
+        for i in (1..POINT_COUNT).rev() {
+            let target = rng.random_int(0, i as i64) as usize;
+            p.swap(i, target);
+        }
+
+        p
+    }
+
+    /* 
+    ** Trilinear interpolation of the gradient contributions.
+    ** The weights use the Hermite cubic 3t^2 - 2t^3 instead of t, which removes the grid artifacts of plain linear blending.
+    */
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+        let mut accum = 0.0;
+
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, gradient) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight_v = Vec3(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                           * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                           * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                           * gradient.dot(weight_v);
+                }
+            }
+        }
+
+        accum
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn zero_on_lattice() {
+        let perlin = Perlin::new();
+
+        assert_float_absolute_eq!(perlin.noise(Vec3(0.0, 0.0, 0.0)), 0.0);
+        assert_float_absolute_eq!(perlin.noise(Vec3(3.0, -7.0, 12.0)), 0.0);
+    }
+
+    #[test]
+    fn bounded_and_deterministic() {
+        let perlin = Perlin::new();
+        let mut rng = Rng::new(5);
+
+        for _ in 0..1000 {
+            let point = Vec3(rng.random_within(-50.0, 50.0), rng.random_within(-50.0, 50.0), rng.random_within(-50.0, 50.0));
+            let noise = perlin.noise(point);
+
+            assert!((-1.0..=1.0).contains(&noise));
+            assert_eq!(noise, perlin.noise(point));
+            assert!(perlin.turb(point, 7) >= 0.0);
+        }
+    }
+}
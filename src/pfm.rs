@@ -0,0 +1,111 @@
+use std::io::{self, Read, Write};
+
+use crate::image::Image;
+use crate::ppm::{invalid_data, sample_count, Header};
+use crate::Vec3;
+
+/*
+** Portable Float Map: a "PF" (color) header, the size, and a scale whose sign gives the byte order (negative = little-endian).
+** Then one 32-bit float per channel, with the rows stored from the bottom of the image to the top.
+** The values are the linear radiance, without any clamping or gamma correction.
+*/
+pub fn write_pfm<W: Write>(image: &Image, mut out: W) -> io::Result<()> {
+    writeln!(out, "PF")?;
+    writeln!(out, "{0} {1}", image.width(), image.height())?;
+    writeln!(out, "-1.0")?;
+
+    for j in (0..image.height()).rev() {
+        for i in 0..image.width() {
+            let pixel_color = image.pixel(i, j);
+            for channel in [pixel_color.x(), pixel_color.y(), pixel_color.z()] {
+                out.write_all(&(channel as f32).to_le_bytes())?;
+            }
+        }
+    }
+
+    out.flush()
+}
+
+/* Reads a color ("PF") or grayscale ("Pf") PFM in either byte order */
+pub fn read_pfm<R: Read>(mut input: R) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut header = Header::new(&data);
+    let channels = match header.token()? {
+        "PF" => 3,
+        "Pf" => 1,
+        magic => return Err(invalid_data(format!("not a PFM file (magic number {:?})", magic)))
+    };
+
+    let width: usize = header.number()?;
+    let height: usize = header.number()?;
+    let scale: f64 = header.number()?;
+    let little_endian = scale < 0.0;
+
+    let raw = header.binary_data(sample_count(width, height, 4 * channels)?)?;
+    let values: Vec<f64> = raw.chunks(4).map(|bytes| {
+        let bytes = bytes.try_into().unwrap();
+        (if little_endian { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }) as f64
+    }).collect();
+
+    let mut image = Image::new(width, height);
+    for (index, pixel) in values.chunks(channels).enumerate() {
+        let color = if channels == 3 { Vec3(pixel[0], pixel[1], pixel[2]) } else { Vec3(pixel[0], pixel[0], pixel[0]) };
+        /* Rows are stored bottom to top */
+        image.set_pixel(index % width, height - 1 - index / width, color);
+    }
+
+    Ok(image)
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    #[test]
+    fn pfm_output() {
+        let mut image = Image::new(1, 2);
+        image.set_pixel(0, 0, Vec3(2.5, 0.0, 0.0));
+        image.set_pixel(0, 1, Vec3(0.0, 0.0, 1.0));
+
+        let mut out = Vec::new();
+        write_pfm(&image, &mut out).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+
+        /* Bottom row comes first */
+        let data = &out[header.len()..];
+        assert_eq!(data.len(), 2 * 3 * 4);
+        assert_eq!(f32::from_le_bytes(data[8..12].try_into().unwrap()), 1.0);
+        assert_eq!(f32::from_le_bytes(data[12..16].try_into().unwrap()), 2.5);
+
+        let read = read_pfm(&out[..]).unwrap();
+        assert_eq!(read.pixel(0, 0), Vec3(2.5, 0.0, 0.0));
+        assert_eq!(read.pixel(0, 1), Vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn big_endian_grayscale() {
+        let mut data = b"Pf\n2 1\n1.0\n".to_vec();
+        data.extend_from_slice(&0.5f32.to_be_bytes());
+        data.extend_from_slice(&4.0f32.to_be_bytes());
+
+        let image = read_pfm(&data[..]).unwrap();
+
+        assert_eq!(image.pixel(0, 0), Vec3(0.5, 0.5, 0.5));
+        assert_eq!(image.pixel(1, 0), Vec3(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn oversized_header() {
+        assert!(read_pfm("PF\n4294967296 4294967296\n-1.0\n".as_bytes()).is_err());
+        assert!(read_pfm("PF\n100000 100000\n-1.0\nabcd".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::mesh::{Mesh, MeshData, MeshFace};
+use crate::ppm::invalid_data;
+use crate::Vec3;
+
+/*
+** Stanford PLY loader, for ASCII and binary little-endian files.
+** Reads vertex positions, normals (nx, ny, nz), texture coordinates (u, v or s, t), colors (red, green, blue)
+** and the face list, which is triangulated. Any other element or property is skipped.
+** To use the vertex colors as albedo, give it a Lambertian with a VertexColorTexture.
+** Format ref: https://paulbourke.net/dataformats/ply/
+*/
+pub fn load_ply<P: AsRef<Path>>(path: P, material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let data = fs::read(path)?;
+
+    Ok(Mesh::new(parse_ply(&data, material)?))
+}
+
+pub fn parse_ply(data: &[u8], material: Arc<dyn Material>) -> io::Result<MeshData> {
+    let (header, body_start) = parse_header(data)?;
+    let mut reader: Box<dyn ValueReader> = match header.format {
+        Format::Ascii => {
+            let body = std::str::from_utf8(&data[body_start..]).map_err(|_| invalid_data("PLY body is not valid text".to_string()))?;
+            Box::new(AsciiReader { tokens: body.split_ascii_whitespace() })
+        }
+        Format::BinaryLittleEndian => Box::new(BinaryReader { data: &data[body_start..], pos: 0 })
+    };
+
+    let mut mesh = MeshData {
+        materials: vec![material],
+        groups: vec!["default".to_string()],
+        ..Default::default()
+    };
+
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(element, reader.as_mut(), &mut mesh)?,
+            "face" => read_faces(element, reader.as_mut(), &mut mesh)?,
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        read_property(property, reader.as_mut())?;
+                    }
+                }
+            }
+        }
+    }
+
+    if mesh.faces.is_empty() {
+        return Err(invalid_data("PLY file has no faces".to_string()));
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> io::Result<ScalarType> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::I8),
+            "uchar" | "uint8" => Ok(ScalarType::U8),
+            "short" | "int16" => Ok(ScalarType::I16),
+            "ushort" | "uint16" => Ok(ScalarType::U16),
+            "int" | "int32" => Ok(ScalarType::I32),
+            "uint" | "uint32" => Ok(ScalarType::U32),
+            "float" | "float32" => Ok(ScalarType::F32),
+            "double" | "float64" => Ok(ScalarType::F64),
+            _ => Err(invalid_data(format!("unknown PLY type {:?}", name)))
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(ScalarType),
+    /* A count of type .0 followed by that many values of type .1 */
+    List(ScalarType, ScalarType)
+}
+
+struct Property {
+    name: String,
+    kind: PropertyKind
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+enum Format {
+    Ascii,
+    BinaryLittleEndian
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>
+}
+
+/* Returns the header and the offset where the element data starts */
+fn parse_header(data: &[u8]) -> io::Result<(Header, usize)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let end = data[pos..].iter().position(|&byte| byte == b'\n')
+            .ok_or_else(|| invalid_data("PLY header has no end_header".to_string()))? + pos;
+        let line = std::str::from_utf8(&data[pos..end]).map_err(|_| invalid_data("PLY header is not valid text".to_string()))?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let is_first_line = pos == 0;
+        pos = end + 1;
+
+        if is_first_line {
+            if tokens != ["ply"] {
+                return Err(invalid_data("not a PLY file".to_string()));
+            }
+            continue;
+        }
+
+        match tokens[..] {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, _] => return Err(invalid_data(format!("unsupported PLY format {:?}", other))),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid_data(format!("invalid element count {:?}", count)))?,
+                properties: Vec::new()
+            }),
+            ["property", "list", count_type, value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("property before any element".to_string()))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List(ScalarType::parse(count_type)?, ScalarType::parse(value_type)?)
+                });
+            }
+            ["property", value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("property before any element".to_string()))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ScalarType::parse(value_type)?)
+                });
+            }
+            ["end_header"] => break,
+            _ => {} // comment, obj_info...
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid_data("PLY header has no format".to_string()))?;
+    Ok((Header { format, elements }, pos))
+}
+
+/* Reads the next value of the element data, whatever the encoding */
+trait ValueReader {
+    fn read(&mut self, scalar_type: ScalarType) -> io::Result<f64>;
+}
+
+struct AsciiReader<'a> {
+    tokens: std::str::SplitAsciiWhitespace<'a>
+}
+
+impl ValueReader for AsciiReader<'_> {
+    fn read(&mut self, _scalar_type: ScalarType) -> io::Result<f64> {
+        let token = self.tokens.next().ok_or_else(|| invalid_data("PLY data ends early".to_string()))?;
+        token.parse().map_err(|_| invalid_data(format!("expected a number, found {:?}", token)))
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl ValueReader for BinaryReader<'_> {
+    fn read(&mut self, scalar_type: ScalarType) -> io::Result<f64> {
+        let size = scalar_type.size();
+        let bytes = self.data.get(self.pos..self.pos + size).ok_or_else(|| invalid_data("PLY data ends early".to_string()))?;
+        self.pos += size;
+
+        Ok(match scalar_type {
+            ScalarType::I8 => bytes[0] as i8 as f64,
+            ScalarType::U8 => bytes[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::F64 => f64::from_le_bytes(bytes.try_into().unwrap())
+        })
+    }
+}
+
+/* Scalars are returned as a single value list */
+fn read_property(property: &Property, reader: &mut dyn ValueReader) -> io::Result<Vec<f64>> {
+    match property.kind {
+        PropertyKind::Scalar(scalar_type) => Ok(vec![reader.read(scalar_type)?]),
+        PropertyKind::List(count_type, value_type) => {
+            let count = reader.read(count_type)? as usize;
+            (0..count).map(|_| reader.read(value_type)).collect()
+        }
+    }
+}
+
+fn read_vertices(element: &Element, reader: &mut dyn ValueReader, mesh: &mut MeshData) -> io::Result<()> {
+    let index_of = |names: &[&str]| element.properties.iter().position(|property| names.contains(&property.name.as_str()));
+    let index_of_all = |names: [&[&str]; 3]| Some([index_of(names[0])?, index_of(names[1])?, index_of(names[2])?]);
+
+    let position = index_of_all([&["x"], &["y"], &["z"]]).ok_or_else(|| invalid_data("PLY vertices have no x, y, z".to_string()))?;
+    let normal = index_of_all([&["nx"], &["ny"], &["nz"]]);
+    let color = index_of_all([&["red", "r"], &["green", "g"], &["blue", "b"]]);
+    let uv = index_of(&["u", "s", "texture_u"]).zip(index_of(&["v", "t", "texture_v"]));
+
+    /* Integer colors go from 0 to the maximum of their type, float ones from 0 to 1 */
+    let color_scale = color.map(|[r, _, _]| match element.properties[r].kind {
+        PropertyKind::Scalar(ScalarType::U8) => 1.0 / 255.0,
+        PropertyKind::Scalar(ScalarType::U16) => 1.0 / 65535.0,
+        _ => 1.0
+    });
+
+    let mut values = Vec::with_capacity(element.properties.len());
+    for _ in 0..element.count {
+        values.clear();
+        for property in &element.properties {
+            values.push(read_property(property, reader)?.first().copied().unwrap_or_default());
+        }
+
+        let vec3 = |[x, y, z]: [usize; 3]| Vec3(values[x], values[y], values[z]);
+        mesh.positions.push(vec3(position));
+        if let Some(normal) = normal {
+            mesh.normals.push(vec3(normal));
+        }
+        if let (Some(color), Some(scale)) = (color, color_scale) {
+            mesh.colors.push(scale * vec3(color));
+        }
+        if let Some((u, v)) = uv {
+            mesh.uvs.push((values[u], values[v]));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(element: &Element, reader: &mut dyn ValueReader, mesh: &mut MeshData) -> io::Result<()> {
+    let indices_property = element.properties.iter()
+        .position(|property| property.name == "vertex_indices" || property.name == "vertex_index")
+        .ok_or_else(|| invalid_data("PLY faces have no vertex_indices".to_string()))?;
+
+    /* Normals and uvs are per vertex in PLY, so they share the position indices */
+    let has_normals = !mesh.normals.is_empty();
+    let has_uvs = !mesh.uvs.is_empty();
+
+    for _ in 0..element.count {
+        let mut indices = Vec::new();
+        for (p, property) in element.properties.iter().enumerate() {
+            let values = read_property(property, reader)?;
+            if p == indices_property {
+                /* Checked before the cast, which would turn -1 into a valid looking 0 */
+                indices = values.into_iter().map(|index| {
+                    if index < 0.0 || index.fract() != 0.0 {
+                        return Err(invalid_data(format!("invalid PLY vertex index {}", index)));
+                    }
+                    Ok(index as usize)
+                }).collect::<io::Result<_>>()?;
+            }
+        }
+
+        if let Some(&index) = indices.iter().find(|&&index| index >= mesh.positions.len()) {
+            return Err(invalid_data(format!("PLY face uses vertex {} but there are {}", index, mesh.positions.len())));
+        }
+
+        // Triangulate the polygon as a fan around its first vertex.
+        for k in 1..indices.len().saturating_sub(1) {
+            let corners = [indices[0], indices[k], indices[k + 1]];
+            mesh.faces.push(MeshFace {
+                positions: corners,
+                normals: has_normals.then_some(corners),
+                uvs: has_uvs.then_some(corners),
+                material: 0,
+                group: 0
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::Interval;
+    use crate::material::Lambertian;
+    use crate::texture::VertexColorTexture;
+    use crate::Ray;
+
+    fn vertex_color_material() -> Arc<dyn Material> {
+        Arc::new(Lambertian { texture: Arc::new(VertexColorTexture { fallback: Vec3(0.5, 0.5, 0.5) }) })
+    }
+
+    const ASCII_SQUARE: &str = "ply
+format ascii 1.0
+comment a unit square at z = -1
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 -1 255 0 0
+1 0 -1 255 0 0
+1 1 -1 0 0 255
+0 1 -1 0 0 255
+4 0 1 2 3
+";
+
+    #[test]
+    fn ascii_with_colors() {
+        let mesh = parse_ply(ASCII_SQUARE.as_bytes(), vertex_color_material()).unwrap();
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.colors[2], Vec3(0.0, 0.0, 1.0));
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.faces[1].positions, [0, 2, 3]);
+        assert_eq!(mesh.faces[0].normals, None);
+
+        /* Halfway up the square, the color is halfway between red and blue */
+        let mesh = Mesh::new(mesh);
+        let ray = Ray {
+            orig: Vec3(0.25, 0.5, 0.0),
+            dir: Vec3(0.0, 0.0, -1.0)
+        };
+        let mut hit_record = HitRecord::default();
+        assert!(mesh.hit(ray, Interval(0.001, f64::INFINITY), &mut hit_record));
+        let color = hit_record.color.unwrap();
+        assert_float_absolute_eq!(color.x(), 0.5);
+        assert_float_absolute_eq!(color.z(), 0.5);
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        let mut data = b"ply
+format binary_little_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+element extra 1
+property list uchar short stuff
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+end_header
+".to_vec();
+        for (x, y) in [(0.0f64, 0.0f64), (1.0, 0.0), (0.0, 1.0)] {
+            for value in [x, y, -1.0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in [0.0f32, 0.0, 1.0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        // The "extra" element, skipped: 2 shorts.
+        data.extend_from_slice(&[2, 1, 0, 2, 0]);
+        // The face: flags, then 3 indices.
+        data.extend_from_slice(&[7, 3]);
+        for index in [0u32, 1, 2] {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mesh = parse_ply(&data, vertex_color_material()).unwrap();
+
+        assert_eq!(mesh.positions[1], Vec3(1.0, 0.0, -1.0));
+        assert_eq!(mesh.normals[2], Vec3(0.0, 0.0, 1.0));
+        assert!(mesh.colors.is_empty());
+        assert_eq!(mesh.faces[0].positions, [0, 1, 2]);
+        assert_eq!(mesh.faces[0].normals, Some([0, 1, 2]));
+
+        /* Cut the data short */
+        assert!(parse_ply(&data[..data.len() - 2], vertex_color_material()).is_err());
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert!(parse_ply(b"obj\n", vertex_color_material()).is_err());
+        assert!(parse_ply(b"ply\nformat binary_big_endian 1.0\nend_header\n", vertex_color_material()).is_err());
+        let bad_index = ASCII_SQUARE.replace("4 0 1 2 3", "3 0 1 9");
+        assert!(parse_ply(bad_index.as_bytes(), vertex_color_material()).is_err());
+
+        for face in ["3 0 1 -1", "3 0 1 1.5"] {
+            let bad_index = ASCII_SQUARE.replace("4 0 1 2 3", face);
+            let error = parse_ply(bad_index.as_bytes(), vertex_color_material()).err().unwrap();
+            assert!(error.to_string().starts_with("invalid PLY vertex index"), "{face}: {error}");
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use std::io::{self, Write};
+
+use crate::image::{encode_color, Image};
+
+/* Bits per color channel of the encoded PNG */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen
+}
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/*
+** PNG is a signature followed by chunks: IHDR (size and pixel format), IDAT (zlib compressed scanlines) and IEND.
+** Spec ref: https://www.w3.org/TR/png/
+*/
+pub fn write_png<W: Write>(image: &Image, bit_depth: BitDepth, mut out: W) -> io::Result<()> {
+    let (depth_byte, max_value, bytes_per_channel) = match bit_depth {
+        BitDepth::Eight => (8u8, 255, 1),
+        BitDepth::Sixteen => (16u8, 65535, 2)
+    };
+
+    out.write_all(&SIGNATURE)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height() as u32).to_be_bytes());
+    /* Bit depth, color type 2 (RGB), compression, filter and interlace methods */
+    header.extend_from_slice(&[depth_byte, 2, 0, 0, 0]);
+    write_chunk(&mut out, b"IHDR", &header)?;
+
+    let bytes_per_pixel = 3 * bytes_per_channel;
+    let row_len = image.width() * bytes_per_pixel;
+    let mut scanlines = Vec::with_capacity(image.height() * (row_len + 1));
+    let mut previous = vec![0u8; row_len];
+    let mut row = Vec::with_capacity(row_len);
+
+    for j in 0..image.height() {
+        row.clear();
+        for i in 0..image.width() {
+            for channel in encode_color(image.pixel(i, j), max_value) {
+                /* 16-bit samples are stored most significant byte first */
+                match bit_depth {
+                    BitDepth::Eight => row.push(channel as u8),
+                    BitDepth::Sixteen => row.extend_from_slice(&(channel as u16).to_be_bytes())
+                }
+            }
+        }
+
+        filter_row(&row, &previous, bytes_per_pixel, &mut scanlines);
+        std::mem::swap(&mut previous, &mut row);
+    }
+
+    write_chunk(&mut out, b"IDAT", &zlib_compress(&scanlines))?;
+    write_chunk(&mut out, b"IEND", &[])?;
+
+    out.flush()
+}
+
+/* Each chunk is: data length, type, data, and a CRC of the type and data */
+fn write_chunk<W: Write>(out: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(chunk_type)?;
+    out.write_all(data)?;
+
+    let crc = crc32(crc32(0, chunk_type), data);
+    out.write_all(&crc.to_be_bytes())
+}
+
+/*
+** Filtering replaces each byte by its difference to a prediction from the neighbouring bytes, which compresses better.
+** We try every filter type and keep the one with the smallest sum of absolute differences, the heuristic suggested by the spec.
+*/
+fn filter_row(row: &[u8], previous: &[u8], bytes_per_pixel: usize, out: &mut Vec<u8>) {
+    let mut best: Option<(u64, u8, Vec<u8>)> = None;
+
+    for filter_type in 0..5u8 {
+        let filtered: Vec<u8> = (0..row.len()).map(|x| {
+            let a = if x >= bytes_per_pixel { row[x - bytes_per_pixel] } else { 0 };
+            let b = previous[x];
+            let c = if x >= bytes_per_pixel { previous[x - bytes_per_pixel] } else { 0 };
+
+            let prediction = match filter_type {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                _ => paeth(a, b, c)
+            };
+            row[x].wrapping_sub(prediction)
+        }).collect();
+
+        let cost = filtered.iter().map(|&byte| (byte as i8).unsigned_abs() as u64).sum();
+        if best.as_ref().map_or(true, |(best_cost, _, _)| cost < *best_cost) {
+            best = Some((cost, filter_type, filtered));
+        }
+    }
+
+    let (_, filter_type, filtered) = best.unwrap();
+    out.push(filter_type);
+    out.extend_from_slice(&filtered);
+}
+
+/* Predicts with whichever of left, up or upper-left is closest to left + up - upper_left */
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/*
+** Zlib stream holding a single DEFLATE block compressed with the fixed Huffman codes.
+** Repeated byte sequences are replaced by (length, distance) back references found with a hash of the next 3 bytes.
+** Not as small as a full encoder with dynamic codes, but much smaller than the raw scanlines.
+** DEFLATE ref: https://www.rfc-editor.org/rfc/rfc1951
+*/
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32768;
+    const MIN_MATCH: usize = 3;
+    const MAX_MATCH: usize = 258;
+    const HASH_SIZE: usize = 1 << 15;
+
+    let mut bits = BitWriter::default();
+    /* CMF (deflate, 32K window) and FLG, chosen so the 16-bit header is a multiple of 31 */
+    bits.bytes.extend_from_slice(&[0x78, 0x01]);
+
+    /* BFINAL = 1, BTYPE = 01 (fixed Huffman codes) */
+    bits.write(1, 1);
+    bits.write(1, 2);
+
+    let hash = |pos: usize| {
+        let key = (data[pos] as usize) << 16 | (data[pos + 1] as usize) << 8 | data[pos + 2] as usize;
+        (key.wrapping_mul(2654435761) >> 7) & (HASH_SIZE - 1)
+    };
+
+    /* Most recent position of each hashed 3-byte prefix */
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let mut match_len = 0;
+        let mut match_dist = 0;
+
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(pos);
+            let candidate = head[h];
+            head[h] = pos;
+
+            if candidate != usize::MAX && pos - candidate <= WINDOW {
+                let max_len = MAX_MATCH.min(data.len() - pos);
+                let len = (0..max_len).take_while(|&k| data[candidate + k] == data[pos + k]).count();
+                if len >= MIN_MATCH {
+                    match_len = len;
+                    match_dist = pos - candidate;
+                }
+            }
+        }
+
+        if match_len > 0 {
+            bits.write_length(match_len);
+            bits.write_distance(match_dist);
+
+            /* Index the skipped positions too, so later matches can refer to them */
+            for skipped in pos + 1..(pos + match_len).min(data.len().saturating_sub(MIN_MATCH - 1)) {
+                head[hash(skipped)] = skipped;
+            }
+            pos += match_len;
+        }
+        else {
+            bits.write_literal(data[pos] as u16);
+            pos += 1;
+        }
+    }
+
+    // End of block
+    bits.write_literal(256);
+
+    let mut bytes = bits.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+/* DEFLATE packs values starting from the least significant bit of each byte */
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32
+}
+
+/* Base lengths and extra bits of the length codes 257..=285 */
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/* Base distances and extra bits of the distance codes 0..=29 */
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.buffer |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /* Huffman codes are the exception: they are packed starting from their most significant bit */
+    fn write_code(&mut self, code: u32, count: u32) {
+        let reversed = code.reverse_bits() >> (32 - count);
+        self.write(reversed, count);
+    }
+
+    /* Fixed Huffman code of a literal/length symbol (0..=287) */
+    fn write_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8)
+        }
+    }
+
+    fn write_length(&mut self, length: usize) {
+        let code = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+        self.write_literal(257 + code as u16);
+        self.write((length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code]);
+    }
+
+    /* Distance codes all use 5 bits in the fixed code */
+    fn write_distance(&mut self, distance: usize) {
+        let code = DIST_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+        self.write_code(code as u32, 5);
+        self.write((distance - DIST_BASE[code] as usize) as u32, DIST_EXTRA[code]);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageFormat;
+    use crate::Vec3;
+
+    #[test]
+    fn checksums() {
+        /* Reference values from the zlib implementation */
+        assert_eq!(crc32(0, b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn png_layout() {
+        let mut image = Image::new(3, 2);
+        image.set_pixel(1, 1, Vec3(1.0, 1.0, 1.0));
+
+        let mut out = Vec::new();
+        write_png(&image, BitDepth::Sixteen, &mut out).unwrap();
+
+        assert_eq!(out[..8], SIGNATURE);
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(out[16..24], [0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(out[24], 16);
+        assert_eq!(&out[out.len() - 8..out.len() - 4], b"IEND");
+    }
+
+    /* Reads DEFLATE bits, least significant first */
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize // In bits
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for k in 0..count {
+                value |= (((self.bytes[self.pos / 8] >> (self.pos % 8)) & 1) as u32) << k;
+                self.pos += 1;
+            }
+            value
+        }
+
+        /* Huffman codes come most significant bit first */
+        fn code(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |code, _| (code << 1) | self.bits(1))
+        }
+    }
+
+    /* Decoder for the fixed Huffman block our encoder writes, to check the stream actually holds the scanlines */
+    fn inflate(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(stream[..2], [0x78, 0x01]);
+        let mut reader = BitReader { bytes: &stream[2..], pos: 0 };
+        assert_eq!((reader.bits(1), reader.bits(2)), (1, 1), "expected a single, final, fixed Huffman block");
+
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            let mut symbol = reader.code(7);
+            if symbol <= 0x17 {
+                symbol += 256;
+            }
+            else {
+                symbol = (symbol << 1) | reader.bits(1);
+                symbol = match symbol {
+                    0x30..=0xbf => symbol - 0x30,
+                    0xc0..=0xc7 => symbol - 0xc0 + 280,
+                    _ => ((symbol << 1) | reader.bits(1)) - 0x190 + 144
+                };
+            }
+
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let index = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index]) as usize;
+                    let index = reader.code(5) as usize;
+                    let distance = DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index]) as usize;
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                }
+            }
+        }
+
+        let checksum = &stream[2 + reader.pos.div_ceil(8)..];
+        assert_eq!(checksum, adler32(&out).to_be_bytes());
+        out
+    }
+
+    /* Undoes the per-row filters, leaving the raw samples */
+    fn unfilter(scanlines: &[u8], row_len: usize, bytes_per_pixel: usize) -> Vec<u8> {
+        let mut pixels: Vec<u8> = Vec::new();
+        for (j, line) in scanlines.chunks(row_len + 1).enumerate() {
+            for x in 0..row_len {
+                let a = if x >= bytes_per_pixel { pixels[j * row_len + x - bytes_per_pixel] } else { 0 };
+                let b = if j > 0 { pixels[(j - 1) * row_len + x] } else { 0 };
+                let c = if j > 0 && x >= bytes_per_pixel { pixels[(j - 1) * row_len + x - bytes_per_pixel] } else { 0 };
+                let prediction = match line[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c)
+                };
+                pixels.push(line[1 + x].wrapping_add(prediction));
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn idat_holds_the_pixels() {
+        /* Gradients and repeated rows, so the filters and back references all get used */
+        let mut image = Image::new(37, 9);
+        for j in 0..9 {
+            for i in 0..37 {
+                let shade = if j % 3 == 0 { 0.5 } else { i as f64 / 36.0 };
+                image.set_pixel(i, j, Vec3(shade, (j as f64 / 8.0).powi(2), 1.0 - shade));
+            }
+        }
+
+        for (bit_depth, max_value, bytes_per_channel) in [(BitDepth::Eight, 255, 1), (BitDepth::Sixteen, 65535, 2)] {
+            let mut out = Vec::new();
+            write_png(&image, bit_depth, &mut out).unwrap();
+
+            /* Chunks after the signature: length, type, data, CRC */
+            let mut pos = 8;
+            let mut idat = None;
+            while pos < out.len() {
+                let len = u32::from_be_bytes(out[pos..pos + 4].try_into().unwrap()) as usize;
+                let chunk = &out[pos + 4..pos + 8 + len];
+                assert_eq!(out[pos + 8 + len..pos + 12 + len], crc32(0, chunk).to_be_bytes());
+                if &chunk[..4] == b"IDAT" {
+                    idat = Some(&chunk[4..]);
+                }
+                pos += 12 + len;
+            }
+
+            let row_len = 37 * 3 * bytes_per_channel;
+            let pixels = unfilter(&inflate(idat.unwrap()), row_len, 3 * bytes_per_channel);
+
+            let expected: Vec<u8> = image.pixels().iter()
+                .flat_map(|&pixel| encode_color(pixel, max_value))
+                .flat_map(|channel| (channel as u16).to_be_bytes()[2 - bytes_per_channel..].to_vec())
+                .collect();
+            assert!(pixels == expected);
+        }
+    }
+
+    #[test]
+    fn sixteen_bits_by_format() {
+        let path = std::env::temp_dir().join(format!("png-test-{}.png", std::process::id()));
+
+        /* The extension alone gives 8 bits, the format picks 16 */
+        Image::new(2, 2).save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[24], 8);
+        Image::new(2, 2).save_as(&path, ImageFormat::Png(BitDepth::Sixteen)).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[24], 16);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{self, Read, Write};
+
+use crate::image::{encode_color, gamma_to_linear, Image};
+use crate::Vec3;
+
+/* Plain (ASCII) PPM: a "P3" header followed by one "r g b" line per pixel */
+pub fn write_p3<W: Write>(image: &Image, mut out: W) -> io::Result<()> {
+    writeln!(out, "P3")?;
+    writeln!(out, "{0} {1}", image.width(), image.height())?;
+    writeln!(out, "255")?;
+
+    for pixel_color in image.pixels() {
+        let [rbyte, gbyte, bbyte] = encode_color(*pixel_color, 255);
+        writeln!(out, "{} {} {}", rbyte, gbyte, bbyte)?;
+    }
+
+    out.flush()
+}
+
+/* Binary PPM: a "P6" header followed by 3 raw bytes per pixel */
+pub fn write_p6<W: Write>(image: &Image, mut out: W) -> io::Result<()> {
+    write!(out, "P6\n{0} {1}\n255\n", image.width(), image.height())?;
+
+    let mut data = Vec::with_capacity(3 * image.pixels().len());
+    for pixel_color in image.pixels() {
+        data.extend(encode_color(*pixel_color, 255).map(|channel| channel as u8));
+    }
+    out.write_all(&data)?;
+
+    out.flush()
+}
+
+/*
+** Reads a P3 or P6 PPM into linear radiance.
+** The samples are assumed to be gamma 2 encoded, like the ones our writers produce, so they are squared back.
+*/
+pub fn read_ppm<R: Read>(mut input: R) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut header = Header::new(&data);
+    let magic = header.token()?;
+    if magic != "P3" && magic != "P6" {
+        return Err(invalid_data(format!("not a PPM file (magic number {:?})", magic)));
+    }
+
+    let width = header.number()?;
+    let height = header.number()?;
+    let max_value = header.number()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data(format!("invalid PPM maximum value {}", max_value)));
+    }
+
+    let samples = sample_count(width, height, 3)?;
+    let values: Vec<usize> = if magic == "P3" {
+        /* Every sample takes at least a byte, so a corrupt size is caught before allocating for it */
+        if samples > header.remaining() {
+            return Err(invalid_data("file is shorter than its header says".to_string()));
+        }
+        (0..samples).map(|_| header.number()).collect::<io::Result<_>>()?
+    }
+    else {
+        /* Samples take 2 bytes, most significant first, when they don't fit in one */
+        let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+        let raw = header.binary_data(sample_count(width, height, 3 * bytes_per_sample)?)?;
+        raw.chunks(bytes_per_sample).map(|sample| sample.iter().fold(0, |value, &byte| (value << 8) | byte as usize)).collect()
+    };
+
+    let decode = |value: usize| gamma_to_linear(value.min(max_value) as f64 / max_value as f64);
+
+    let mut image = Image::new(width, height);
+    for (index, rgb) in values.chunks(3).enumerate() {
+        image.set_pixel(index % width, index / width, Vec3(decode(rgb[0]), decode(rgb[1]), decode(rgb[2])));
+    }
+
+    Ok(image)
+}
+
+pub fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/* Number of values of a width x height image, or an error when a corrupt header makes it overflow */
+pub fn sample_count(width: usize, height: usize, per_pixel: usize) -> io::Result<usize> {
+    width.checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(per_pixel))
+        .ok_or_else(|| invalid_data(format!("image size {}x{} is too large", width, height)))
+}
+
+/*
+** The text header shared by the PNM family (PPM) and PFM: whitespace separated tokens, with "#" starting a comment.
+** A single whitespace character separates the header from the binary data that may follow.
+*/
+pub struct Header<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Header<'a> {
+    pub fn new(data: &'a [u8]) -> Header<'a> {
+        Header { data, pos: 0 }
+    }
+
+    pub fn token(&mut self) -> io::Result<&'a str> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(byte) if byte.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&byte| byte != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(invalid_data("unexpected end of file".to_string()))
+            }
+        }
+
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.data[start..self.pos]).map_err(|_| invalid_data("header is not valid text".to_string()))
+    }
+
+    pub fn number<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let token = self.token()?;
+        token.parse().map_err(|_| invalid_data(format!("expected a number, found {:?}", token)))
+    }
+
+    /* Skips the single whitespace after the last header token and returns the next len bytes */
+    pub fn binary_data(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let start = self.pos + 1;
+        start.checked_add(len)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| invalid_data("file is shorter than its header says".to_string()))
+    }
+
+    /* Bytes left after the current position */
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    #[test]
+    fn p3_output() {
+        let mut image = Image::new(2, 1);
+        image.set_pixel(0, 0, Vec3(1.0, 0.25, 0.0));
+        image.set_pixel(1, 0, Vec3(4.0, -1.0, 0.0));
+
+        let mut out = Vec::new();
+        write_p3(&image, &mut out).unwrap();
+
+        /* 0.25 is gamma corrected to 0.5. Out of range values are clamped */
+        assert_eq!(String::from_utf8(out).unwrap(), "P3\n2 1\n255\n255 128 0\n255 0 0\n");
+    }
+
+    #[test]
+    fn p6_round_trip() {
+        let mut image = Image::new(2, 2);
+        image.set_pixel(0, 0, Vec3(1.0, 0.25, 0.0));
+        image.set_pixel(1, 1, Vec3(0.5, 0.5, 0.5));
+
+        let mut out = Vec::new();
+        write_p6(&image, &mut out).unwrap();
+        assert_eq!(out.len(), "P6\n2 2\n255\n".len() + 2 * 2 * 3);
+
+        let read = read_ppm(&out[..]).unwrap();
+        assert_eq!(read.width(), 2);
+        assert_eq!(read.height(), 2);
+        assert_float_absolute_eq!(read.pixel(0, 0).y(), 0.25, 0.01);
+        assert_float_absolute_eq!(read.pixel(1, 1).x(), 0.5, 0.01);
+        assert_float_absolute_eq!(read.pixel(1, 0).z(), 0.0);
+    }
+
+    #[test]
+    fn p3_with_comments() {
+        let data = "P3\n# a comment\n2 1 # trailing\n15\n15 0 0  0 15 0\n";
+
+        let image = read_ppm(data.as_bytes()).unwrap();
+
+        assert_eq!(image.pixel(0, 0), Vec3(1.0, 0.0, 0.0));
+        assert_eq!(image.pixel(1, 0), Vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn truncated_file() {
+        assert!(read_ppm("P6\n2 2\n255\nabc".as_bytes()).is_err());
+        assert!(read_ppm("P3\n2 1\n255\n1 2 3".as_bytes()).is_err());
+        assert!(read_ppm("P4\n1 1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn oversized_header() {
+        /* The sizes overflow, or would need gigabytes the file doesn't have: both must fail without allocating */
+        for data in [
+            "P6\n4294967296 4294967296\n255\n",
+            "P6\n18446744073709551615 1\n65535\n",
+            "P3\n4294967296 4294967296\n255\n1 2 3\n",
+            "P3\n100000 100000\n255\n1 2 3\n",
+            "P6\n100000 100000\n255\nabc"
+        ] {
+            let error = read_ppm(data.as_bytes()).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{data:?}");
+        }
+    }
+
+    #[test]
+    fn read_reference_render() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/image-sec3.ppm");
+
+        let image = read_ppm(std::fs::File::open(path).unwrap()).unwrap();
+
+        assert_eq!(image.width(), 256);
+        assert_eq!(image.height(), 256);
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::Vec3;
+use crate::Ray;
+
+/* 
+** Parallelogram with corner q and edges u and v, so its corners are q, q + u, q + v and q + u + v.
+** Used for walls, floors, area lights and boxes.
+*/
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3, // n / (n . n), used to find the planar coordinates of a hit point
+    material: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64 // The quad's plane is normal . p = d
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Quad {
+        let n = u.cross(v);
+        let normal = n.unit();
+
+        // Compute the bounding box of all four vertices.
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            material,
+            bbox: Aabb::enclosing(&bbox_diagonal1, &bbox_diagonal2),
+            normal,
+            d: normal.dot(q)
+        }
+    }
+
+    /* Given the hit point in plane coordinates, return false if it is outside the primitive */
+    fn is_interior(a: f64, b: f64) -> bool {
+        let unit_interval = Interval(0.0, 1.0);
+
+        unit_interval.contains(a) && unit_interval.contains(b)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        let denom = self.normal.dot(ray.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // Return false if the hit point parameter t is outside the ray interval.
+        let t = (self.d - self.normal.dot(ray.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        /* Determine if the hit point lies within the planar shape using its plane coordinates (alpha, beta),
+        ** where intersection = q + alpha * u + beta * v
+        */
+        let intersection = ray.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = self.w.dot(planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hitpt_vector));
+
+        if !Self::is_interior(alpha, beta) {
+            return false;
+        }
+
+        // Ray hits the 2D shape; set the rest of the hit record and return true.
+        hit_record.t = t;
+        hit_record.point = intersection;
+        hit_record.u = alpha;
+        hit_record.v = beta;
+        hit_record.material = Some(self.material.clone());
+        hit_record.color = None;
+        hit_record.set_face_normal(ray, self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/* Returns the 3D box (six sides) that contains the two opposite vertices a & b */
+pub fn make_box(a: Vec3, b: Vec3, material: Arc<dyn Material>) -> HittableList {
+    let mut sides: HittableList = Default::default();
+
+    // Construct the two opposite vertices with the minimum and maximum coordinates.
+    let min = a.min(b);
+    let max = a.max(b);
+
+    let dx = Vec3(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3(0.0, 0.0, max.z() - min.z());
+
+    sides.add(Arc::new(Quad::new(Vec3(min.x(), min.y(), max.z()), dx, dy, material.clone()))); // front
+    sides.add(Arc::new(Quad::new(Vec3(max.x(), min.y(), max.z()), -dz, dy, material.clone()))); // right
+    sides.add(Arc::new(Quad::new(Vec3(max.x(), min.y(), min.z()), -dx, dy, material.clone()))); // back
+    sides.add(Arc::new(Quad::new(Vec3(min.x(), min.y(), min.z()), dz, dy, material.clone()))); // left
+    sides.add(Arc::new(Quad::new(Vec3(min.x(), max.y(), max.z()), dx, -dz, material.clone()))); // top
+    sides.add(Arc::new(Quad::new(Vec3(min.x(), min.y(), min.z()), dx, dz, material))); // bottom
+
+    sides
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    fn floor() -> Quad {
+        /* 2 x 4 floor at y = -1, starting at x = -1, z = -1 */
+        Quad::new(Vec3(-1.0, -1.0, -1.0), Vec3(2.0, 0.0, 0.0), Vec3(0.0, 0.0, -4.0), Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))))
+    }
+
+    #[test]
+    fn hit_inside() {
+        let quad = floor();
+        let ray = Ray {
+            orig: Vec3(0.5, 1.0, -2.0),
+            dir: Vec3(0.0, -1.0, 0.0)
+        };
+        let mut hit_record = HitRecord::default();
+
+        assert!(quad.hit(ray, Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert_float_absolute_eq!(hit_record.t, 2.0);
+        assert_float_absolute_eq!(hit_record.u, 0.75);
+        assert_float_absolute_eq!(hit_record.v, 0.25);
+        assert!(hit_record.front_face);
+        assert_eq!(hit_record.normal, Vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn miss_outside_and_parallel() {
+        let quad = floor();
+        let mut hit_record = HitRecord::default();
+
+        let outside = Ray {
+            orig: Vec3(1.5, 1.0, -2.0),
+            dir: Vec3(0.0, -1.0, 0.0)
+        };
+        let parallel = Ray {
+            orig: Vec3(0.0, -1.0, 0.0),
+            dir: Vec3(0.0, 0.0, -1.0)
+        };
+
+        assert!(!quad.hit(outside, Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert!(!quad.hit(parallel, Interval(0.001, f64::INFINITY), &mut hit_record));
+    }
+
+    #[test]
+    fn flat_bounding_box_is_padded() {
+        let bbox = floor().bounding_box();
+
+        assert!(bbox.y.size() > 0.0);
+        assert_float_absolute_eq!(bbox.z.min(), -5.0);
+    }
+}
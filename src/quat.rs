@@ -0,0 +1,181 @@
+use std::ops::Mul;
+
+use crate::matrix::{Mat3, Mat4};
+use crate::Vec3;
+
+/*
+** Quaternion w + xi + yj + zk. Unit quaternions represent rotations, and unlike matrices or Euler angles
+** they can be interpolated smoothly (slerp), which is what camera and object animation need.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quat {
+    pub fn identity() -> Quat {
+        Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 }
+    }
+
+    /* Rotation of "degrees" around the axis, counterclockwise when looking down the axis (same convention as Mat4::rotation) */
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Quat {
+        let a = axis.unit();
+        let (sin_half, cos_half) = (degrees.to_radians() / 2.0).sin_cos();
+
+        Quat { w: cos_half, x: a.x() * sin_half, y: a.y() * sin_half, z: a.z() * sin_half }
+    }
+
+    pub fn vector(&self) -> Vec3 {
+        Vec3(self.x, self.y, self.z)
+    }
+
+    pub fn dot(&self, q: Quat) -> f64 {
+        self.w * q.w + self.x * q.x + self.y * q.y + self.z * q.z
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quat {
+        self.scale(1.0 / self.length())
+    }
+
+    pub fn conjugate(&self) -> Quat {
+        Quat { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    /* For unit quaternions this is the conjugate, i.e. the opposite rotation */
+    pub fn inverse(&self) -> Quat {
+        self.conjugate().scale(1.0 / self.dot(*self))
+    }
+
+    /* Rotates a vector by a unit quaternion: q v q* expanded, without building the intermediate products */
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let u = self.vector();
+        let t = 2.0 * u.cross(v);
+        v + self.w * t + u.cross(t)
+    }
+
+    pub fn to_mat3(self) -> Mat3 {
+        let q = self.normalize();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+
+        Mat3([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)]
+        ])
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        Mat4::from_mat3(self.to_mat3(), Vec3::default())
+    }
+
+    /*
+    ** Spherical linear interpolation: constant angular speed from a (t = 0) to b (t = 1).
+    ** q and -q are the same rotation, so we flip b when needed to take the shortest way around.
+    */
+    pub fn slerp(a: Quat, b: Quat, t: f64) -> Quat {
+        let mut b = b;
+        let mut cos_theta = a.dot(b);
+        if cos_theta < 0.0 {
+            b = b.scale(-1.0);
+            cos_theta = -cos_theta;
+        }
+
+        /* Nearly identical rotations: sin(theta) goes to 0, so a normalized lerp is both safe and accurate */
+        if cos_theta > 0.9995 {
+            return a.scale(1.0 - t).add(b.scale(t)).normalize();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let weight_a = ((1.0 - t) * theta).sin() / sin_theta;
+        let weight_b = (t * theta).sin() / sin_theta;
+
+        a.scale(weight_a).add(b.scale(weight_b))
+    }
+
+    fn scale(&self, s: f64) -> Quat {
+        Quat { w: self.w * s, x: self.x * s, y: self.y * s, z: self.z * s }
+    }
+
+    fn add(&self, q: Quat) -> Quat {
+        Quat { w: self.w + q.w, x: self.x + q.x, y: self.y + q.y, z: self.z + q.z }
+    }
+}
+
+/* Hamilton product. a * b rotates by b first and then by a, like matrices */
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, rhs: Quat) -> Quat {
+        let (a, b) = (self.vector(), rhs.vector());
+        let v = self.w * b + rhs.w * a + a.cross(b);
+
+        Quat { w: self.w * rhs.w - a.dot(b), x: v.x(), y: v.y(), z: v.z() }
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert_float_absolute_eq!(a.x(), b.x());
+        assert_float_absolute_eq!(a.y(), b.y());
+        assert_float_absolute_eq!(a.z(), b.z());
+    }
+
+    #[test]
+    fn rotation_matches_matrix() {
+        let q = Quat::from_axis_angle(Vec3(1.0, 2.0, -1.0), 75.0);
+        let m = Mat4::rotation(Vec3(1.0, 2.0, -1.0), 75.0);
+        let v = Vec3(0.3, -4.0, 2.5);
+
+        assert_vec_eq(q.rotate(v), m.transform_vector(v));
+        assert_vec_eq(q.to_mat4().transform_vector(v), m.transform_vector(v));
+        assert_vec_eq(q.inverse().rotate(q.rotate(v)), v);
+    }
+
+    #[test]
+    fn composition() {
+        let first = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), 90.0);
+        let second = Quat::from_axis_angle(Vec3(1.0, 0.0, 0.0), 90.0);
+
+        /* x goes to -z, and then -z goes to y */
+        assert_vec_eq((second * first).rotate(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quat::identity();
+        let b = Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), 90.0);
+
+        assert_vec_eq(Quat::slerp(a, b, 0.0).rotate(Vec3(1.0, 0.0, 0.0)), Vec3(1.0, 0.0, 0.0));
+        assert_vec_eq(Quat::slerp(a, b, 1.0).rotate(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 1.0, 0.0));
+
+        let half = Quat::slerp(a, b, 0.5);
+        let expected = std::f64::consts::FRAC_1_SQRT_2;
+        assert_vec_eq(half.rotate(Vec3(1.0, 0.0, 0.0)), Vec3(expected, expected, 0.0));
+        assert_float_absolute_eq!(half.length(), 1.0);
+
+        /* -b is the same rotation, and must give the same (short way around) result */
+        let negated = Quat { w: -b.w, x: -b.x, y: -b.y, z: -b.z };
+        assert_vec_eq(Quat::slerp(a, negated, 0.5).rotate(Vec3(1.0, 0.0, 0.0)), Vec3(expected, expected, 0.0));
+    }
+}
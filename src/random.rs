@@ -1,13 +1,129 @@
-use rand::Rng;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 
-pub fn random() -> f64 {
-    /* Rand ref:
-    ** https://rust-lang-nursery.github.io/rust-cookbook/algorithms/randomness.html#generate-random-numbers-within-a-range
-    */
-    let mut rng = rand::thread_rng();
-    rng.gen_range(0.0..1.0)
+/*
+** Small, fast pseudo-random generator (PCG32, XSH RR variant). Renders create one per pixel from a global
+** seed, so a given seed always gives the same image, whatever the number of threads.
+** Ref: https://www.pcg-random.org/download.html
+*/
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64
 }
 
-pub fn random_within(min: f64, max: f64) -> f64 {
-    min + (max-min)*random()
-}
\ No newline at end of file
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /* Seeded by the OS: std hashers get random keys for every process */
+    pub fn from_entropy() -> Rng {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+
+    /* The generator of pixel (i, j). Neighbouring pixels get unrelated sequences */
+    pub fn for_pixel(seed: u64, i: usize, j: usize) -> Rng {
+        Rng::new(mix(seed ^ mix(((j as u64) << 32) | i as u64)))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /* Uniform in [0, 1), with the full 53 bits of precision */
+    pub fn random(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn random_within(&mut self, min: f64, max: f64) -> f64 {
+        min + (max-min)*self.random()
+    }
+
+    /* Random integer in [min, max], both ends included */
+    pub fn random_int(&mut self, min: i64, max: i64) -> i64 {
+        self.random_within(min as f64, (max + 1) as f64).floor() as i64
+    }
+}
+
+/* SplitMix64 finalizer: turns nearby numbers (like pixel indices) into unrelated ones */
+pub(crate) fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+thread_local! {
+    /* Generator of the free functions below, used while building scenes (e.g. Perlin noise tables) */
+    static RNG: RefCell<Rng> = RefCell::new(Rng::from_entropy());
+}
+
+/* Restarts the current thread's generator from a fixed seed, so the numbers that follow are always the same */
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = Rng::new(seed));
+}
+
+/* A new generator, seeded from the current thread's one */
+pub fn fork() -> Rng {
+    RNG.with(|rng| Rng::new(rng.borrow_mut().next_u64()))
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_sequence() {
+        /* First outputs of the reference pcg32_srandom_r(42, initseq) with the default increment */
+        let mut rng = Rng::new(42);
+        assert_eq!([rng.next_u32(), rng.next_u32(), rng.next_u32()], [0xc2f57bd6, 0x6b07c4a9, 0x72b7b29b]);
+    }
+
+    #[test]
+    fn reproducible() {
+        let mut a = Rng::for_pixel(7, 10, 20);
+        let mut b = Rng::for_pixel(7, 10, 20);
+        let mut other = Rng::for_pixel(7, 11, 20);
+
+        let sequence: Vec<f64> = (0..100).map(|_| a.random()).collect();
+        assert_eq!(sequence, (0..100).map(|_| b.random()).collect::<Vec<f64>>());
+        assert_ne!(sequence, (0..100).map(|_| other.random()).collect::<Vec<f64>>());
+        assert!(sequence.iter().all(|x| (0.0..1.0).contains(x)));
+
+        seed(3);
+        let first = fork().next_u64();
+        seed(3);
+        assert_eq!(first, fork().next_u64());
+    }
+
+    #[test]
+    fn uniform() {
+        let mut rng = Rng::new(1);
+        let mut buckets = [0; 10];
+        for _ in 0..100_000 {
+            buckets[rng.random_int(0, 9) as usize] += 1;
+        }
+
+        assert!(buckets.iter().all(|&count| (9_500..10_500).contains(&count)));
+    }
+}
@@ -0,0 +1,513 @@
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+use crate::random::{mix, Rng};
+use crate::Vec3;
+
+/*
+** Source of the "random" numbers of a path. Each pixel sample asks for its numbers dimension by dimension,
+** in the same order: the position in the pixel (2D), the lens (2D), then the decisions of every bounce.
+** Better samplers than plain random numbers spread the samples of a pixel evenly in each dimension,
+** so the image converges with fewer samples.
+*/
+pub trait Sampler {
+    /* Starts sample number "index" of pixel (i, j). The dimensions restart from the first one */
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32);
+
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/* The samplers a Camera can use */
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SamplerType {
+    /* Plain random numbers */
+    #[default]
+    Independent,
+    /* Jittered grid: one sample per stratum. Works best with square sample counts (16, 64, 100, ...) */
+    Stratified,
+    /* Halton sequence, randomized per pixel */
+    Halton,
+    /* Owen-scrambled Sobol, padded from 2D. Works best with powers of 2 sample counts */
+    Sobol
+}
+
+impl SamplerType {
+    pub fn from_name(name: &str) -> Option<SamplerType> {
+        match name.to_ascii_lowercase().as_str() {
+            "independent" | "random" => Some(SamplerType::Independent),
+            "stratified" => Some(SamplerType::Stratified),
+            "halton" => Some(SamplerType::Halton),
+            "sobol" => Some(SamplerType::Sobol),
+            _ => None
+        }
+    }
+
+    /* The same seed always gives the same numbers */
+    pub fn create(self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(samples_per_pixel, seed))
+        }
+    }
+}
+
+/* Every sample of every pixel gets its own generator, so samples can be taken in any order */
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Rng
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> IndependentSampler {
+        IndependentSampler { seed, rng: Rng::new(seed) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, (i, j): (usize, usize), index: u32) {
+        self.rng = Rng::for_pixel(self.seed ^ mix(index as u64), i, j);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.random()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.rng.random(), self.rng.random())
+    }
+}
+
+/*
+** Splits each dimension in as many strata as there are samples, and gives every sample its own stratum,
+** jittered inside it. 2D dimensions use a grid as square as the sample count allows.
+** Each dimension shuffles which sample gets which stratum, so the dimensions aren't correlated.
+*/
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    grid: (u32, u32), // Columns and rows of the 2D strata
+    seed: u64,
+    pixel: (usize, usize),
+    index: u32,
+    dimension: u64,
+    rng: Rng // Jitter inside the strata
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> StratifiedSampler {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        /* Largest divisor up to the square root, e.g. 10x10 for 100 samples and 4x8 for 32 */
+        let columns = (1..=samples_per_pixel)
+            .take_while(|d| d * d <= samples_per_pixel)
+            .filter(|&d| samples_per_pixel % d == 0)
+            .last()
+            .unwrap_or(1);
+
+        StratifiedSampler {
+            samples_per_pixel,
+            grid: (columns, samples_per_pixel / columns),
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            rng: Rng::new(seed)
+        }
+    }
+
+    /* Stratum of the current sample in the current dimension */
+    fn stratum(&mut self) -> u32 {
+        let (i, j) = self.pixel;
+        let permutation = hash(&[i as u64, j as u64, self.dimension, self.seed]) as u32;
+        self.dimension += 1;
+
+        permutation_element(self.index, self.samples_per_pixel, permutation)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, (i, j): (usize, usize), index: u32) {
+        self.pixel = (i, j);
+        self.index = index % self.samples_per_pixel;
+        self.dimension = 0;
+        self.rng = Rng::for_pixel(self.seed ^ mix(index as u64), i, j);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let stratum = self.stratum();
+        (stratum as f64 + self.rng.random()) / self.samples_per_pixel as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let stratum = self.stratum();
+        let (columns, rows) = self.grid;
+        let (x, y) = (stratum % columns, stratum / columns);
+
+        ((x as f64 + self.rng.random()) / columns as f64, (y as f64 + self.rng.random()) / rows as f64)
+    }
+}
+
+/*
+** Dimension d is the radical inverse of the sample index in the d-th prime base. Every pixel shifts each dimension
+** by its own random offset (Cranley-Patterson rotation), which keeps the even spacing but hides the pattern.
+** Large bases are poorly distributed, so dimensions past the primes table fall back to random numbers.
+*/
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    index: u64,
+    dimension: usize,
+    rng: Rng // Dimensions past the primes table
+}
+
+const HALTON_DIMENSIONS: usize = 256;
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler { seed, pixel: (0, 0), index: 0, dimension: 0, rng: Rng::new(seed) }
+    }
+
+    fn next(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension >= HALTON_DIMENSIONS {
+            return self.rng.random();
+        }
+
+        let (i, j) = self.pixel;
+        let shift = to_unit(hash(&[i as u64, j as u64, dimension as u64, self.seed]));
+        let value = radical_inverse(primes()[dimension], self.index) + shift;
+
+        (value - value.floor()).min(ONE_MINUS_EPSILON)
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, (i, j): (usize, usize), index: u32) {
+        self.pixel = (i, j);
+        self.index = index as u64;
+        self.dimension = 0;
+        self.rng = Rng::for_pixel(self.seed ^ mix(index as u64), i, j);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.next()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.next(), self.next())
+    }
+}
+
+/*
+** Only the first two Sobol dimensions, which form a (0, 2)-sequence: with 2^k samples, every one of the
+** 2^k equal-area boxes of the unit square gets exactly one point. Higher dimensions are "padded" with
+** more copies of those two, each with its own Owen scrambling and its own shuffle of the sample order,
+** so they are as well distributed as the first ones but uncorrelated with each other.
+** Ref: https://pbr-book.org/4ed/Sampling_and_Reconstruction/Sobol_Samplers
+*/
+pub struct SobolSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: (usize, usize),
+    index: u32,
+    dimension: u64
+}
+
+impl SobolSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> SobolSampler {
+        SobolSampler { samples_per_pixel: samples_per_pixel.max(1), seed, pixel: (0, 0), index: 0, dimension: 0 }
+    }
+
+    /* Shuffled sample index, and the scrambling seed, of the current dimension */
+    fn next_dimension(&mut self) -> (u32, u64) {
+        let (i, j) = self.pixel;
+        let hash = hash(&[i as u64, j as u64, self.dimension, self.seed]);
+        self.dimension += 1;
+
+        (permutation_element(self.index, self.samples_per_pixel, hash as u32), hash)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, (i, j): (usize, usize), index: u32) {
+        self.pixel = (i, j);
+        self.index = index % self.samples_per_pixel;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, hash) = self.next_dimension();
+        to_unit_u32(owen_scramble(index.reverse_bits(), hash as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, hash) = self.next_dimension();
+        (
+            to_unit_u32(owen_scramble(index.reverse_bits(), hash as u32)),
+            to_unit_u32(owen_scramble(sobol_second_dimension(index), (hash >> 32) as u32))
+        )
+    }
+}
+
+/* Largest f64 below 1 */
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/* Uniform direction on the unit sphere */
+pub fn sample_uniform_sphere((u1, u2): (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+
+    Vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+/*
+** Uniform point in the unit disk (z = 0). The concentric mapping (Shirley-Chiu) keeps neighbouring samples
+** together, so the stratification of the input survives.
+*/
+pub fn sample_uniform_disk((u1, u2): (f64, f64)) -> Vec3 {
+    let (x, y) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+    if x == 0.0 && y == 0.0 {
+        return Vec3(0.0, 0.0, 0.0);
+    }
+
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, PI / 4.0 * (y / x))
+    }
+    else {
+        (y, PI / 2.0 - PI / 4.0 * (x / y))
+    };
+
+    Vec3(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0, |h, &value| mix(h ^ value))
+}
+
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn to_unit_u32(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+/* Base b digits of n, mirrored around the decimal point: 0.d0 d1 d2... */
+fn radical_inverse(base: u64, mut n: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_power = 1.0;
+    let mut reversed = 0.0;
+
+    while n > 0 {
+        inv_base_power *= inv_base;
+        reversed += (n % base) as f64 * inv_base_power;
+        n /= base;
+    }
+
+    reversed
+}
+
+fn primes() -> &'static [u64] {
+    static PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes: Vec<u64> = Vec::with_capacity(HALTON_DIMENSIONS);
+        let mut candidate = 2;
+        while primes.len() < HALTON_DIMENSIONS {
+            if primes.iter().take_while(|&&p| p * p <= candidate).all(|&p| candidate % p != 0) {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+        primes
+    })
+}
+
+/* Second Sobol dimension (primitive polynomial x + 1), as 32 bits of fraction */
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction: u32 = 1 << 31;
+    while index > 0 {
+        if index & 1 == 1 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/*
+** Owen scrambling: randomly flips each bit depending on the bits above it, which keeps the (0, 2) property.
+** This is the hash-based version of Laine and Karras, as improved by Vegdahl (also used by pbrt-v4).
+*/
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut v = value.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x05526c56);
+    v ^= v.wrapping_mul(0x53a22864);
+    v.reverse_bits()
+}
+
+/*
+** Element i of a random permutation of 0..length, chosen by the seed, without building the permutation.
+** Ref: Kensler, "Correlated Multi-Jittered Sampling" (2013)
+*/
+fn permutation_element(i: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let mut i = i;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | (seed >> 27));
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        /* Values past the length are cycled until they land inside */
+        if i < length {
+            break;
+        }
+    }
+
+    /* In 64 bits: a u32 sum would overflow for large seeds, and the wrapped value would no longer be a permutation */
+    ((i as u64 + seed as u64) % length as u64) as u32
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [SamplerType; 4] = [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol];
+
+    /* Dimension "skip" (after skip 1D draws) of every sample of a pixel */
+    fn samples_2d(sampler_type: SamplerType, samples_per_pixel: u32, skip: usize) -> Vec<(f64, f64)> {
+        let mut sampler = sampler_type.create(samples_per_pixel, 7);
+        (0..samples_per_pixel).map(|index| {
+            sampler.start_pixel_sample((3, 5), index);
+            for _ in 0..skip {
+                sampler.get_1d();
+            }
+            sampler.get_2d()
+        }).collect()
+    }
+
+    #[test]
+    fn reproducible_and_in_range() {
+        for sampler_type in ALL {
+            let first = samples_2d(sampler_type, 16, 3);
+            assert_eq!(first, samples_2d(sampler_type, 16, 3));
+            assert!(first.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+
+            /* Past the Halton primes too */
+            let deep = samples_2d(sampler_type, 4, 300);
+            assert!(deep.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+        }
+    }
+
+    #[test]
+    fn stratification() {
+        /* 16 samples in a 4x4 grid: one per cell, in the pixel dimension and deep in the path */
+        for skip in [0, 5] {
+            for sampler_type in [SamplerType::Stratified, SamplerType::Sobol] {
+                let mut cells: Vec<(u32, u32)> = samples_2d(sampler_type, 16, skip).iter()
+                    .map(|&(x, y)| ((x * 4.0) as u32, (y * 4.0) as u32))
+                    .collect();
+                cells.sort();
+                cells.dedup();
+                assert_eq!(cells.len(), 16, "{sampler_type:?}");
+            }
+        }
+
+        /* Sobol is a (0, 2)-net: also one sample per 16x1 and 1x16 strip */
+        let sobol = samples_2d(SamplerType::Sobol, 16, 2);
+        for strips in [sobol.iter().map(|&(x, _)| (x * 16.0) as u32).collect::<Vec<_>>(), sobol.iter().map(|&(_, y)| (y * 16.0) as u32).collect()] {
+            let mut strips = strips;
+            strips.sort();
+            assert_eq!(strips, (0..16).collect::<Vec<u32>>());
+        }
+
+        /* The first 2 Halton dimensions are spread evenly, up to the random shift */
+        let mut halton: Vec<f64> = samples_2d(SamplerType::Halton, 16, 0).iter().map(|&(x, _)| x).collect();
+        halton.sort_by(f64::total_cmp);
+        for pair in halton.windows(2) {
+            assert_float_absolute_eq!(pair[1] - pair[0], 1.0 / 16.0);
+        }
+    }
+
+    #[test]
+    fn permutations() {
+        /* Seeds close to u32::MAX used to overflow when shifting the result */
+        for seed in [0, 12345, u32::MAX - 3, u32::MAX] {
+            for length in [1, 2, 7, 16, 100, 1000] {
+                let mut seen = vec![false; length as usize];
+                for i in 0..length {
+                    let element = permutation_element(i, length, seed) as usize;
+                    assert!(!seen[element], "{element} twice for length {length} and seed {seed}");
+                    seen[element] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn faster_convergence() {
+        /* Integral of a smooth function over the square, estimated in many pixels with 64 samples each */
+        let integrand = |(x, y): (f64, f64)| (x * y).sin() + x * x;
+        let exact = 0.573145078;
+        let error = |sampler_type: SamplerType| -> f64 {
+            let mut sampler = sampler_type.create(64, 11);
+            (0..100).map(|pixel| {
+                let estimate = (0..64).map(|index| {
+                    sampler.start_pixel_sample((pixel, 0), index);
+                    integrand(sampler.get_2d())
+                }).sum::<f64>() / 64.0;
+                (estimate - exact).powi(2)
+            }).sum::<f64>()
+        };
+
+        let independent = error(SamplerType::Independent);
+        for sampler_type in [SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+            assert!(error(sampler_type) < independent / 10.0, "{sampler_type:?}");
+        }
+    }
+
+    #[test]
+    fn warping() {
+        for &u in &[(0.0, 0.0), (0.3, 0.9), (0.999, 0.5)] {
+            assert_float_absolute_eq!(sample_uniform_sphere(u).length(), 1.0);
+            assert!(sample_uniform_disk(u).length() <= 1.0 + 1e-12);
+        }
+
+        assert_float_absolute_eq!(sample_uniform_disk((1.0, 0.5)).x(), 1.0);
+        assert_eq!(sample_uniform_disk((0.5, 0.5)), Vec3(0.0, 0.0, 0.0));
+        assert_eq!(SamplerType::from_name("Sobol"), Some(SamplerType::Sobol));
+        assert_eq!(SamplerType::from_name("blue-noise"), None);
+    }
+}
@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::camera::{Camera, CameraError};
+use crate::environment::{Background, EnvironmentMap};
+use crate::hittable::{Hittable, HittableList};
+use crate::json::{self, Json};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::mesh::Mesh;
+use crate::obj::parse_obj;
+use crate::perlin::Perlin;
+use crate::ply::parse_ply;
+use crate::quad::{make_box, Quad};
+use crate::sampler::SamplerType;
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture, TurbulenceTexture};
+use crate::transform::{Instance, Transform};
+use crate::triangle::Triangle;
+use crate::Vec3;
+
+/*
+** A scene read from a JSON file:
+**
+** {
+**   "camera": { "image_width": 400, "look_from": [-2, 2, 1], "background": [0, 0, 0], ... },
+**   "textures": { "checker": { "type": "checker", "scale": 0.3, "even": [0.2, 0.3, 0.1], "odd": [0.9, 0.9, 0.9] } },
+**   "materials": { "ground": { "type": "lambertian", "texture": "checker" }, "glass": { "type": "dielectric", "refraction_index": 1.5 } },
+**   "objects": [ { "type": "sphere", "center": [0, -100.5, -1], "radius": 100, "material": "ground" } ]
+** }
+**
+** Every section is optional, and so is every camera setting (missing ones keep the Camera defaults).
+** Textures and materials are named, and referenced by name further down the file. Relative paths
+** (images, meshes) are relative to the scene file. scenes/three_spheres.json is a complete example.
+*/
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: PathBuf, error: io::Error },
+    Syntax(json::ParseError),
+    UnknownTexture { at: String, name: String },
+    UnknownMaterial { at: String, name: String },
+    /* Missing, mistyped or out of range value, at a path such as "objects[2].radius" */
+    Invalid { at: String, message: String }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneError::Syntax(error) => write!(f, "syntax error at {error}"),
+            SceneError::UnknownTexture { at, name } => write!(f, "{at}: unknown texture \"{name}\""),
+            SceneError::UnknownMaterial { at, name } => write!(f, "{at}: unknown material \"{name}\""),
+            SceneError::Invalid { at, message } => write!(f, "{at}: {message}")
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { error, .. } => Some(error),
+            SceneError::Syntax(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl Scene {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|error| SceneError::Io { path: path.to_path_buf(), error })?;
+
+        Self::parse(&source, path.parent().unwrap_or(Path::new(".")))
+    }
+
+    /* Files referenced by the scene are looked up relative to base_dir */
+    pub fn parse(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+        let document = json::parse(source).map_err(SceneError::Syntax)?;
+        let root = Node { json: &document, at: String::new() };
+        root.allow_only(&["camera", "textures", "materials", "objects"])?;
+
+        let mut loader = Loader {
+            base_dir,
+            textures: HashMap::new(),
+            materials: HashMap::new()
+        };
+
+        let camera = match root.optional("camera") {
+            Some(node) => loader.camera(&node)?,
+            None => Camera::default()
+        };
+
+        if let Some(textures) = root.optional("textures") {
+            for (name, node) in textures.members()? {
+                let texture = loader.texture(&node)?;
+                loader.textures.insert(name, texture);
+            }
+        }
+
+        if let Some(materials) = root.optional("materials") {
+            for (name, node) in materials.members()? {
+                let material = loader.material(&node)?;
+                loader.materials.insert(name, material);
+            }
+        }
+
+        let mut world = HittableList::default();
+        if let Some(objects) = root.optional("objects") {
+            for node in objects.elements()? {
+                world.add(loader.object(&node)?);
+            }
+        }
+
+        Ok(Scene { camera, world })
+    }
+}
+
+/* Name tables filled while reading the file, in order */
+struct Loader<'a> {
+    base_dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>
+}
+
+impl Loader<'_> {
+    fn camera(&self, node: &Node) -> Result<Camera, SceneError> {
+        node.allow_only(&[
+            "aspect_ratio", "image_width", "samples_per_pixel", "max_depth", "vfov", "look_from", "look_at", "vup",
+            "defocus_angle", "focus_dist", "threads", "sampler", "background"
+        ])?;
+        let mut camera = Camera::default();
+
+        if let Some(value) = node.optional("aspect_ratio") {
+            camera.aspect_ratio = value.positive()?;
+        }
+        if let Some(value) = node.optional("image_width") {
+            camera.image_width = value.count(1)? as i64;
+        }
+        if let Some(value) = node.optional("samples_per_pixel") {
+            camera.samples_per_pixel = value.count(1)? as u32;
+        }
+        if let Some(value) = node.optional("max_depth") {
+            camera.max_depth = value.count(1)? as u32;
+        }
+        if let Some(value) = node.optional("vfov") {
+            camera.vfov = value.number()?;
+            if camera.vfov <= 0.0 || camera.vfov >= 180.0 {
+                return Err(value.invalid("must be between 0 and 180 degrees"));
+            }
+        }
+        if let Some(value) = node.optional("look_from") {
+            camera.look_from = value.vec3()?;
+        }
+        if let Some(value) = node.optional("look_at") {
+            camera.look_at = value.vec3()?;
+        }
+        if let Some(value) = node.optional("vup") {
+            camera.vup = value.vec3()?;
+        }
+        if let Some(value) = node.optional("defocus_angle") {
+            camera.defocus_angle = value.non_negative()?;
+        }
+        if let Some(value) = node.optional("focus_dist") {
+            camera.focus_dist = value.positive()?;
+        }
+        if let Some(value) = node.optional("threads") {
+            camera.threads = value.count(0)?;
+        }
+        if let Some(value) = node.optional("sampler") {
+            let name = value.string()?;
+            camera.sampler = SamplerType::from_name(name).ok_or_else(|| value.invalid(&format!("unknown sampler \"{name}\"")))?;
+        }
+        if let Some(value) = node.optional("background") {
+            camera.background = self.background(&value)?;
+        }
+
+        /* Reported at the value to change, rather than when rendering */
+        camera.validate().map_err(|error| match (error, node.optional("vup")) {
+            (CameraError::VupParallelToView, Some(value)) => value.invalid("must not be parallel to the view direction"),
+            (CameraError::VupParallelToView, None) => node.invalid("the default vup [0, 1, 0] is parallel to the view direction, set another one"),
+            (CameraError::SamePoints, _) => node.invalid(&error.to_string())
+        })?;
+
+        Ok(camera)
+    }
+
+    /* A bare color is a solid background */
+    fn background(&self, node: &Node) -> Result<Background, SceneError> {
+        if node.json.as_array().is_some() {
+            return Ok(Background::Solid(node.color()?));
+        }
+
+        match node.kind()? {
+            "solid" => {
+                node.allow_only(&["type", "color"])?;
+                Ok(Background::Solid(node.field("color")?.color()?))
+            }
+            "gradient" => {
+                node.allow_only(&["type", "bottom", "top"])?;
+                Ok(Background::Gradient {
+                    bottom: node.field("bottom")?.color()?,
+                    top: node.field("top")?.color()?
+                })
+            }
+            "environment" => {
+                node.allow_only(&["type", "path", "rotation", "intensity"])?;
+                let path = self.path(&node.field("path")?)?;
+                let rotation = node.optional("rotation").map_or(Ok(0.0), |value| value.number())?;
+                let intensity = node.optional("intensity").map_or(Ok(1.0), |value| value.non_negative())?;
+
+                let map = EnvironmentMap::load(&path, rotation, intensity).map_err(|error| SceneError::Io { path, error })?;
+                Ok(Background::Environment(Arc::new(map)))
+            }
+            other => Err(node.field("type")?.invalid(&format!("unknown background type \"{other}\"")))
+        }
+    }
+
+    fn texture(&self, node: &Node) -> Result<Arc<dyn Texture>, SceneError> {
+        match node.kind()? {
+            "solid" => {
+                node.allow_only(&["type", "color"])?;
+                Ok(Arc::new(SolidColor { albedo: node.field("color")?.color()? }))
+            }
+            "checker" => {
+                node.allow_only(&["type", "scale", "even", "odd"])?;
+                Ok(Arc::new(CheckerTexture::new(
+                    node.field("scale")?.positive()?,
+                    self.texture_or_color(&node.field("even")?)?,
+                    self.texture_or_color(&node.field("odd")?)?
+                )))
+            }
+            "image" => {
+                node.allow_only(&["type", "path"])?;
+                let path = self.path(&node.field("path")?)?;
+                let texture = ImageTexture::load(&path).map_err(|error| SceneError::Io { path, error })?;
+                Ok(Arc::new(texture))
+            }
+            "noise" => {
+                node.allow_only(&["type", "scale"])?;
+                Ok(Arc::new(NoiseTexture::new(node.field("scale")?.positive()?)))
+            }
+            "turbulence" => {
+                node.allow_only(&["type", "scale", "depth"])?;
+                Ok(Arc::new(TurbulenceTexture {
+                    noise: Perlin::new(),
+                    scale: node.field("scale")?.positive()?,
+                    depth: node.optional("depth").map_or(Ok(7), |value| value.count(1))? as u32
+                }))
+            }
+            other => Err(node.field("type")?.invalid(&format!("unknown texture type \"{other}\"")))
+        }
+    }
+
+    fn material(&self, node: &Node) -> Result<Arc<dyn Material>, SceneError> {
+        match node.kind()? {
+            "lambertian" => {
+                node.allow_only(&["type", "albedo", "texture"])?;
+                Ok(Arc::new(Lambertian { texture: self.color_or_texture(node, "albedo")? }))
+            }
+            "metal" => {
+                node.allow_only(&["type", "albedo", "fuzz"])?;
+                let fuzz = node.optional("fuzz").map_or(Ok(0.0), |value| value.non_negative())?;
+                if fuzz > 1.0 {
+                    return Err(node.field("fuzz")?.invalid("must be between 0 and 1"));
+                }
+                Ok(Arc::new(Metal { albedo: node.field("albedo")?.color()?, fuzz }))
+            }
+            "dielectric" => {
+                node.allow_only(&["type", "refraction_index"])?;
+                Ok(Arc::new(Dielectric { refraction_index: node.field("refraction_index")?.positive()? }))
+            }
+            "diffuse_light" => {
+                node.allow_only(&["type", "emit", "texture"])?;
+                Ok(Arc::new(DiffuseLight { texture: self.color_or_texture(node, "emit")? }))
+            }
+            other => Err(node.field("type")?.invalid(&format!("unknown material type \"{other}\"")))
+        }
+    }
+
+    /* Materials take either a plain color (under color_key) or the name of a texture, but not both */
+    fn color_or_texture(&self, node: &Node, color_key: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        match (node.optional(color_key), node.optional("texture")) {
+            (Some(color), None) => Ok(Arc::new(SolidColor { albedo: color.color()? })),
+            (None, Some(texture)) => self.texture_named(&texture),
+            (Some(_), Some(_)) => Err(node.invalid(&format!("\"{color_key}\" and \"texture\" can't be used together"))),
+            (None, None) => Err(node.invalid(&format!("missing \"{color_key}\" or \"texture\"")))
+        }
+    }
+
+    /* Checker squares are a color, or the name of another texture */
+    fn texture_or_color(&self, node: &Node) -> Result<Arc<dyn Texture>, SceneError> {
+        match node.json {
+            Json::String(_) => self.texture_named(node),
+            _ => Ok(Arc::new(SolidColor { albedo: node.color()? }))
+        }
+    }
+
+    fn texture_named(&self, node: &Node) -> Result<Arc<dyn Texture>, SceneError> {
+        let name = node.string()?;
+        self.textures.get(name).cloned().ok_or_else(|| SceneError::UnknownTexture { at: node.at.clone(), name: name.to_string() })
+    }
+
+    fn material_named(&self, node: &Node) -> Result<Arc<dyn Material>, SceneError> {
+        let name = node.string()?;
+        self.materials.get(name).cloned().ok_or_else(|| SceneError::UnknownMaterial { at: node.at.clone(), name: name.to_string() })
+    }
+
+    fn object(&self, node: &Node) -> Result<Arc<dyn Hittable>, SceneError> {
+        let kind = node.kind()?;
+        let object: Arc<dyn Hittable> = match kind {
+            "sphere" => {
+                node.allow_only(&["type", "center", "radius", "material", "transform"])?;
+                Arc::new(Sphere {
+                    center: node.field("center")?.vec3()?,
+                    radius: node.field("radius")?.positive()?,
+                    material: self.material_named(&node.field("material")?)?
+                })
+            }
+            "quad" => {
+                node.allow_only(&["type", "q", "u", "v", "material", "transform"])?;
+                let (u, v) = (node.field("u")?.vec3()?, node.field("v")?.vec3()?);
+                if u.cross(v).near_zero() {
+                    return Err(node.invalid("\"u\" and \"v\" must not be parallel"));
+                }
+                Arc::new(Quad::new(node.field("q")?.vec3()?, u, v, self.material_named(&node.field("material")?)?))
+            }
+            "box" => {
+                node.allow_only(&["type", "a", "b", "material", "transform"])?;
+                Arc::new(make_box(node.field("a")?.vec3()?, node.field("b")?.vec3()?, self.material_named(&node.field("material")?)?))
+            }
+            "triangle" => {
+                node.allow_only(&["type", "vertices", "material", "transform"])?;
+                let vertices = node.field("vertices")?;
+                let points = vertices.elements()?.iter().map(|vertex| vertex.vec3()).collect::<Result<Vec<_>, _>>()?;
+                let points: [Vec3; 3] = points.try_into().map_err(|_| vertices.invalid("expected 3 vertices"))?;
+                Arc::new(Triangle::new(points, None, None, self.material_named(&node.field("material")?)?))
+            }
+            "mesh" => {
+                node.allow_only(&["type", "path", "material", "transform"])?;
+                Arc::new(self.mesh(node)?)
+            }
+            other => return Err(node.field("type")?.invalid(&format!("unknown object type \"{other}\"")))
+        };
+
+        match node.optional("transform") {
+            Some(transform) => Ok(Arc::new(Instance::new(object, self.transform(&transform)?))),
+            None => Ok(object)
+        }
+    }
+
+    /* .obj or .ply. The material is the one used by faces without a material of their own */
+    fn mesh(&self, node: &Node) -> Result<Mesh, SceneError> {
+        let path_node = node.field("path")?;
+        let path = self.path(&path_node)?;
+        let material = self.material_named(&node.field("material")?)?;
+        let io_error = |error| SceneError::Io { path: path.clone(), error };
+
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+        let data = match extension.as_deref() {
+            Some("obj") => {
+                let source = fs::read_to_string(&path).map_err(io_error)?;
+                parse_obj(&source, path.parent().unwrap_or(Path::new(".")), material).map_err(io_error)?
+            }
+            Some("ply") => parse_ply(&fs::read(&path).map_err(io_error)?, material).map_err(io_error)?,
+            _ => return Err(path_node.invalid("meshes must be .obj or .ply files"))
+        };
+
+        if data.faces.is_empty() {
+            return Err(path_node.invalid("the mesh has no faces"));
+        }
+
+        Ok(Mesh::new(data))
+    }
+
+    /* Scale, then rotate, then translate. Each step is optional */
+    fn transform(&self, node: &Node) -> Result<Transform, SceneError> {
+        node.allow_only(&["scale", "rotate", "translate"])?;
+        let mut transform = Transform::identity();
+
+        if let Some(scale) = node.optional("scale") {
+            let factors = match scale.json {
+                Json::Number(_) => {
+                    let factor = scale.number()?;
+                    Vec3(factor, factor, factor)
+                }
+                _ => scale.vec3()?
+            };
+            if factors.x() == 0.0 || factors.y() == 0.0 || factors.z() == 0.0 {
+                return Err(scale.invalid("scale factors can't be 0"));
+            }
+            transform = transform.then(&Transform::scale(factors));
+        }
+
+        if let Some(rotate) = node.optional("rotate") {
+            rotate.allow_only(&["axis", "degrees"])?;
+            let axis = rotate.field("axis")?;
+            if axis.vec3()?.near_zero() {
+                return Err(axis.invalid("the rotation axis can't be zero"));
+            }
+            transform = transform.then(&Transform::rotate(axis.vec3()?, rotate.field("degrees")?.number()?));
+        }
+
+        if let Some(translate) = node.optional("translate") {
+            transform = transform.then(&Transform::translate(translate.vec3()?));
+        }
+
+        Ok(transform)
+    }
+
+    fn path(&self, node: &Node) -> Result<PathBuf, SceneError> {
+        Ok(self.base_dir.join(node.string()?))
+    }
+}
+
+/* A value of the document, with its path for error messages */
+struct Node<'a> {
+    json: &'a Json,
+    at: String
+}
+
+impl<'a> Node<'a> {
+    fn invalid(&self, message: &str) -> SceneError {
+        let at = if self.at.is_empty() { "scene".to_string() } else { self.at.clone() };
+        SceneError::Invalid { at, message: message.to_string() }
+    }
+
+    fn expected(&self, what: &str) -> SceneError {
+        self.invalid(&format!("expected {what}, found {}", self.json.type_name()))
+    }
+
+    fn child(&self, key: &str, json: &'a Json) -> Node<'a> {
+        let at = if self.at.is_empty() { key.to_string() } else { format!("{}.{}", self.at, key) };
+        Node { json, at }
+    }
+
+    fn optional(&self, key: &str) -> Option<Node<'a>> {
+        self.json.get(key).map(|json| self.child(key, json))
+    }
+
+    fn field(&self, key: &str) -> Result<Node<'a>, SceneError> {
+        if self.json.as_object().is_none() {
+            return Err(self.expected("an object"));
+        }
+        self.optional(key).ok_or_else(|| self.invalid(&format!("missing \"{key}\"")))
+    }
+
+    /* Catches typos, which would otherwise be silently ignored */
+    fn allow_only(&self, keys: &[&str]) -> Result<(), SceneError> {
+        let members = self.json.as_object().ok_or_else(|| self.expected("an object"))?;
+        match members.iter().find(|(name, _)| !keys.contains(&name.as_str())) {
+            Some((name, _)) => Err(self.invalid(&format!("unknown field \"{name}\""))),
+            None => Ok(())
+        }
+    }
+
+    /* The "type" field, which selects what kind of texture, material, etc. this is */
+    fn kind(&self) -> Result<&'a str, SceneError> {
+        self.field("type")?.string()
+    }
+
+    fn members(&self) -> Result<Vec<(String, Node<'a>)>, SceneError> {
+        let members = self.json.as_object().ok_or_else(|| self.expected("an object"))?;
+        Ok(members.iter().map(|(name, json)| (name.clone(), self.child(name, json))).collect())
+    }
+
+    fn elements(&self) -> Result<Vec<Node<'a>>, SceneError> {
+        let values = self.json.as_array().ok_or_else(|| self.expected("an array"))?;
+        Ok(values.iter().enumerate().map(|(i, json)| Node { json, at: format!("{}[{}]", self.at, i) }).collect())
+    }
+
+    fn string(&self) -> Result<&'a str, SceneError> {
+        self.json.as_str().ok_or_else(|| self.expected("a string"))
+    }
+
+    fn number(&self) -> Result<f64, SceneError> {
+        self.json.as_f64().ok_or_else(|| self.expected("a number"))
+    }
+
+    fn positive(&self) -> Result<f64, SceneError> {
+        let value = self.number()?;
+        if value <= 0.0 {
+            return Err(self.invalid("must be greater than 0"));
+        }
+        Ok(value)
+    }
+
+    fn non_negative(&self) -> Result<f64, SceneError> {
+        let value = self.number()?;
+        if value < 0.0 {
+            return Err(self.invalid("can't be negative"));
+        }
+        Ok(value)
+    }
+
+    /* Whole number of at least min */
+    fn count(&self, min: usize) -> Result<usize, SceneError> {
+        let value = self.number()?;
+        if value.fract() != 0.0 || value < min as f64 || value > u32::MAX as f64 {
+            return Err(self.invalid(&format!("expected a whole number of at least {min}")));
+        }
+        Ok(value as usize)
+    }
+
+    fn vec3(&self) -> Result<Vec3, SceneError> {
+        let values = self.json.as_array().ok_or_else(|| self.expected("an array of 3 numbers"))?;
+        match values {
+            [Json::Number(x), Json::Number(y), Json::Number(z)] => Ok(Vec3(*x, *y, *z)),
+            _ => Err(self.invalid("expected an array of 3 numbers"))
+        }
+    }
+
+    /* Linear RGB, so no negative components */
+    fn color(&self) -> Result<Vec3, SceneError> {
+        let color = self.vec3()?;
+        if color.x() < 0.0 || color.y() < 0.0 || color.z() < 0.0 {
+            return Err(self.invalid("color components can't be negative"));
+        }
+        Ok(color)
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::interval::Interval;
+    use crate::Ray;
+
+    fn error_message(source: &str) -> String {
+        match Scene::parse(source, Path::new(".")) {
+            Ok(_) => panic!("the scene should not load"),
+            Err(error) => error.to_string()
+        }
+    }
+
+    #[test]
+    fn load_example_scene() {
+        let scene = Scene::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/three_spheres.json")).unwrap();
+
+        assert_eq!(scene.camera.image_width, 400);
+        assert_eq!(scene.camera.samples_per_pixel, 100);
+        assert_eq!(scene.camera.look_from, Vec3(-2.0, 2.0, 1.0));
+        assert_eq!(scene.world.objects().len(), 5);
+
+        /* Straight down onto the center sphere */
+        let ray = Ray {
+            orig: Vec3(0.0, 5.0, -1.2),
+            dir: Vec3(0.0, -1.0, 0.0)
+        };
+        let mut hit_record = HitRecord::default();
+        assert!(scene.world.hit(ray, Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert_float_absolute_eq!(hit_record.point.y(), 0.5);
+    }
+
+    #[test]
+    fn objects_textures_and_transforms() {
+        let scene = Scene::parse(r#"{
+            "camera": { "background": [0, 0, 0], "sampler": "sobol" },
+            "textures": {
+                "marble": { "type": "noise", "scale": 4 },
+                "checker": { "type": "checker", "scale": 0.5, "even": "marble", "odd": [0.9, 0.9, 0.9] }
+            },
+            "materials": {
+                "floor": { "type": "lambertian", "texture": "checker" },
+                "light": { "type": "diffuse_light", "emit": [4, 4, 4] },
+                "steel": { "type": "metal", "albedo": [0.8, 0.8, 0.8], "fuzz": 0.1 }
+            },
+            "objects": [
+                { "type": "quad", "q": [-1, 0, -1], "u": [2, 0, 0], "v": [0, 0, 2], "material": "floor" },
+                { "type": "box", "a": [0, 0, 0], "b": [1, 1, 1], "material": "steel",
+                  "transform": { "scale": 2, "rotate": { "axis": [0, 1, 0], "degrees": 45 }, "translate": [3, 0, 0] } },
+                { "type": "triangle", "vertices": [[0, 3, 0], [1, 3, 0], [0, 3, 1]], "material": "light" }
+            ]
+        }"#, Path::new(".")).unwrap();
+
+        assert!(matches!(scene.camera.background, Background::Solid(_)));
+        assert_eq!(scene.camera.sampler, SamplerType::Sobol);
+        assert_eq!(scene.world.objects().len(), 3);
+
+        /* The scaled box reaches 2 units up (plus the padding of its flat sides) */
+        let bbox = scene.world.objects()[1].bounding_box();
+        assert_float_absolute_eq!(bbox.y.max(), 2.0, 1e-3);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error_message(r#"{ "objects": [ { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "gold" } ] }"#),
+            "objects[0].material: unknown material \"gold\""
+        );
+        assert_eq!(
+            error_message(r#"{ "materials": { "red": { "type": "lambertian", "texture": "bricks" } } }"#),
+            "materials.red.texture: unknown texture \"bricks\""
+        );
+        assert_eq!(
+            error_message(r#"{ "materials": { "m": { "type": "lambertian", "albedo": [1, 1, 1] } },
+                "objects": [ { "type": "sphere", "center": [0, 0, 0], "radius": -2, "material": "m" } ] }"#),
+            "objects[0].radius: must be greater than 0"
+        );
+        assert_eq!(
+            error_message(r#"{ "camera": { "image_width": 12.5 } }"#),
+            "camera.image_width: expected a whole number of at least 1"
+        );
+        assert_eq!(
+            error_message(r#"{ "camera": { "look_from": [0, 1] } }"#),
+            "camera.look_from: expected an array of 3 numbers"
+        );
+        assert_eq!(
+            error_message(r#"{ "materials": { "glass": { "type": "dielectric", "refractive_index": 1.5 } } }"#),
+            "materials.glass: unknown field \"refractive_index\""
+        );
+        assert_eq!(
+            error_message(r#"{ "materials": { "glass": { "type": "plastic" } } }"#),
+            "materials.glass.type: unknown material type \"plastic\""
+        );
+        assert_eq!(error_message(r#"{ "objects": {} }"#), "objects: expected an array, found an object");
+        assert_eq!(error_message("{ \"camera\": }"), "syntax error at line 1, column 13: unexpected character '}'");
+
+        assert!(error_message(r#"{ "textures": { "earth": { "type": "image", "path": "missing/earthmap.ppm" } } }"#).starts_with("./missing/earthmap.ppm: "));
+    }
+
+    #[test]
+    fn degenerate_camera() {
+        assert_eq!(
+            error_message(r#"{ "camera": { "look_from": [1, 2, 3], "look_at": [1, 2, 3] } }"#),
+            "camera: look_from and look_at must be different points"
+        );
+        assert_eq!(
+            error_message(r#"{ "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1], "vup": [0, 0, 2] } }"#),
+            "camera.vup: must not be parallel to the view direction"
+        );
+        assert_eq!(
+            error_message(r#"{ "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1], "vup": [0, 0, 0] } }"#),
+            "camera.vup: must not be parallel to the view direction"
+        );
+        assert_eq!(
+            error_message(r#"{ "camera": { "look_from": [0, 5, 0], "look_at": [0, 0, 0] } }"#),
+            "camera: the default vup [0, 1, 0] is parallel to the view direction, set another one"
+        );
+
+        /* Looking straight down is fine with another up vector */
+        assert!(Scene::parse(r#"{ "camera": { "look_from": [0, 5, 0], "look_at": [0, 0, 0], "vup": [0, 0, -1] } }"#, Path::new(".")).is_ok());
+    }
+
+    #[test]
+    fn empty_environment_map() {
+        let dir = std::env::temp_dir().join(format!("scene-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("empty.ppm"), "P3\n0 4\n255\n").unwrap();
+
+        /* An error to report, not a panic */
+        let source = r#"{ "camera": { "background": { "type": "environment", "path": "empty.ppm" } } }"#;
+        let error = Scene::parse(source, &dir).err().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(error, SceneError::Io { ref error, .. } if error.kind() == io::ErrorKind::InvalidData));
+    }
+}
@@ -1,5 +1,7 @@
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
+use crate::aabb::Aabb;
 use crate::hittable::Hittable;
 use crate::interval::Interval;
 use crate::material::Material;
@@ -10,7 +12,24 @@ use crate::hittable::HitRecord;
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
-    pub material: Rc<dyn Material>
+    pub material: Arc<dyn Material>
+}
+
+impl Sphere {
+    /* 
+    ** point: a given point on the sphere of radius one, centered at the origin.
+    ** u: returned value [0,1] of angle around the Y axis from X=-1.
+    ** v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    **     <1 0 0> yields <0.50 0.50>       <-1  0  0> yields <0.00 0.50>
+    **     <0 1 0> yields <0.50 1.00>       < 0 -1  0> yields <0.50 0.00>
+    **     <0 0 1> yields <0.25 0.50>       < 0  0 -1> yields <0.75 0.50>
+    */
+    pub fn get_sphere_uv(point: Vec3) -> (f64, f64) {
+        let theta = (-point.y()).acos();
+        let phi = (-point.z()).atan2(point.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
@@ -49,8 +68,42 @@ impl Hittable for Sphere {
         hit_record.point = ray.at(hit_record.t);
         let outward_normal = (hit_record.point - self.center) / self.radius;
         hit_record.set_face_normal(ray, outward_normal);
+        (hit_record.u, hit_record.v) = Self::get_sphere_uv(outward_normal);
         hit_record.material = Some(self.material.clone());
+        hit_record.color = None;
+
+        true
+    }
 
-        return true;
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3(self.radius, self.radius, self.radius);
+        Aabb::from_points(self.center - rvec, self.center + rvec)
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_uv() {
+        let cases = [
+            (Vec3(1.0, 0.0, 0.0), (0.5, 0.5)),
+            (Vec3(-1.0, 0.0, 0.0), (0.0, 0.5)),
+            (Vec3(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Vec3(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (Vec3(0.0, 0.0, 1.0), (0.25, 0.5)),
+            (Vec3(0.0, 0.0, -1.0), (0.75, 0.5))
+        ];
+
+        for (point, (u, v)) in cases {
+            let uv = Sphere::get_sphere_uv(point);
+            assert_float_absolute_eq!(uv.0, u);
+            assert_float_absolute_eq!(uv.1, v);
+        }
     }
 }
\ No newline at end of file
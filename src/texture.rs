@@ -0,0 +1,205 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::hittable::HitRecord;
+use crate::image::Image;
+use crate::perlin::Perlin;
+use crate::ppm::invalid_data;
+use crate::Interval;
+use crate::Vec3;
+
+/* A color that varies over a surface, looked up by the (u, v) surface coordinates and/or the hit point */
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3;
+
+    /* Lookup for a surface hit. Textures that need more than (u, v, point) from the hit override this */
+    fn value_at(&self, hit_record: &HitRecord) -> Vec3 {
+        self.value(hit_record.u, hit_record.v, hit_record.point)
+    }
+}
+
+pub struct SolidColor {
+    pub albedo: Vec3
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: Vec3) -> Vec3 {
+        self.albedo
+    }
+}
+
+/* 
+** Spatial (3D) checker pattern: space is divided in cubes of side "scale", alternating between the two textures.
+** Being based on the hit point, it doesn't need the surface coordinates at all.
+*/
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> CheckerTexture {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Vec3, odd: Vec3) -> CheckerTexture {
+        Self::new(scale, Arc::new(SolidColor { albedo: even }), Arc::new(SolidColor { albedo: odd }))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3 {
+        let x_integer = (self.inv_scale * point.x()).floor() as i64;
+        let y_integer = (self.inv_scale * point.y()).floor() as i64;
+        let z_integer = (self.inv_scale * point.z()).floor() as i64;
+
+        let is_even = (x_integer + y_integer + z_integer) % 2 == 0;
+
+        if is_even { self.even.value(u, v, point) } else { self.odd.value(u, v, point) }
+    }
+}
+
+/* Maps an image over the surface: u goes left to right and v from the bottom of the image to the top */
+pub struct ImageTexture {
+    pub image: Image
+}
+
+impl ImageTexture {
+    /* PPM and PFM headers may give a zero width or height, which leaves nothing to map */
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImageTexture> {
+        let image = Image::load(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(invalid_data(format!("empty texture image ({}x{} pixels)", image.width(), image.height())));
+        }
+
+        Ok(ImageTexture { image })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: Vec3) -> Vec3 {
+        // If we have no texture data, then return solid cyan as a debugging aid.
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return Vec3(0.0, 1.0, 1.0);
+        }
+
+        // Clamp input texture coordinates to [0,1] x [1,0]
+        let u = Interval(0.0, 1.0).clamp(u);
+        let v = 1.0 - Interval(0.0, 1.0).clamp(v);  // Flip V to image coordinates
+
+        let i = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let j = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+
+        self.image.pixel(i, j)
+    }
+}
+
+/* 
+** The color interpolated from the vertices of the mesh that was hit (e.g. a colored PLY scan).
+** Surfaces without vertex colors get the fallback color.
+*/
+pub struct VertexColorTexture {
+    pub fallback: Vec3
+}
+
+impl Texture for VertexColorTexture {
+    fn value(&self, _u: f64, _v: f64, _point: Vec3) -> Vec3 {
+        self.fallback
+    }
+
+    fn value_at(&self, hit_record: &HitRecord) -> Vec3 {
+        hit_record.color.unwrap_or(self.fallback)
+    }
+}
+
+/* 
+** Marble-like pattern: stripes along z whose phase is perturbed by turbulence.
+** scale sets the frequency of the stripes
+*/
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> NoiseTexture {
+        NoiseTexture {
+            noise: Perlin::new(),
+            scale
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, point: Vec3) -> Vec3 {
+        0.5 * (1.0 + (self.scale * point.z() + 10.0 * self.noise.turb(point, 7)).sin()) * Vec3(1.0, 1.0, 1.0)
+    }
+}
+
+/* Plain turbulence, as a gray level. Useful for rougher surfaces such as stone and terrain */
+pub struct TurbulenceTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub depth: u32
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, _u: f64, _v: f64, point: Vec3) -> Vec3 {
+        self.noise.turb(self.scale * point, self.depth) * Vec3(1.0, 1.0, 1.0)
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checker() {
+        let checker = CheckerTexture::from_colors(0.5, Vec3(1.0, 1.0, 1.0), Vec3(0.0, 0.0, 0.0));
+
+        assert_eq!(checker.value(0.0, 0.0, Vec3(0.1, 0.1, 0.1)), Vec3(1.0, 1.0, 1.0));
+        assert_eq!(checker.value(0.0, 0.0, Vec3(0.6, 0.1, 0.1)), Vec3(0.0, 0.0, 0.0));
+        /* Negative coordinates must keep alternating across the origin */
+        assert_eq!(checker.value(0.0, 0.0, Vec3(-0.1, 0.1, 0.1)), Vec3(0.0, 0.0, 0.0));
+        assert_eq!(checker.value(0.0, 0.0, Vec3(-0.1, -0.1, 0.1)), Vec3(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn image_lookup() {
+        let mut image = Image::new(2, 2);
+        image.set_pixel(0, 0, Vec3(1.0, 0.0, 0.0));
+        image.set_pixel(1, 1, Vec3(0.0, 0.0, 1.0));
+        let texture = ImageTexture { image };
+
+        /* v = 1 is the top row of the image */
+        assert_eq!(texture.value(0.0, 1.0, Vec3::default()), Vec3(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(1.0, 0.0, Vec3::default()), Vec3(0.0, 0.0, 1.0));
+        assert_eq!(texture.value(-5.0, 5.0, Vec3::default()), Vec3(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn empty_image() {
+        let path = std::env::temp_dir().join(format!("texture-test-{}.ppm", std::process::id()));
+
+        /* A valid header, without a single pixel */
+        std::fs::write(&path, "P3\n0 4\n255\n").unwrap();
+        assert_eq!(ImageTexture::load(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+
+        /* Built by hand, it shows as cyan instead of panicking */
+        for (width, height) in [(0, 4), (4, 0)] {
+            let texture = ImageTexture { image: Image::new(width, height) };
+            assert_eq!(texture.value(0.5, 0.5, Vec3::default()), Vec3(0.0, 1.0, 1.0));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::matrix::Mat4;
+use crate::quat::Quat;
+use crate::Vec3;
+use crate::Ray;
+
+/*
+** Affine transform, kept as a 4x4 matrix together with its inverse.
+** Every constructor knows its inverse in closed form, so general matrices only get inverted in from_matrix.
+*/
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    m: Mat4,
+    inv: Mat4
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform { m: Mat4::identity(), inv: Mat4::identity() }
+    }
+
+    /* Any invertible affine matrix. Returns None for singular ones */
+    pub fn from_matrix(m: Mat4) -> Option<Transform> {
+        Some(Transform { inv: m.inverse()?, m })
+    }
+
+    pub fn translate(offset: Vec3) -> Transform {
+        Transform {
+            m: Mat4::translation(offset),
+            inv: Mat4::translation(-offset)
+        }
+    }
+
+    /* Non-uniform scale along the axes. No factor can be 0 */
+    pub fn scale(factors: Vec3) -> Transform {
+        Transform {
+            m: Mat4::scaling(factors),
+            inv: Mat4::scaling(Vec3(1.0 / factors.x(), 1.0 / factors.y(), 1.0 / factors.z()))
+        }
+    }
+
+    /* The inverse of a rotation is its transpose */
+    pub fn rotate(axis: Vec3, degrees: f64) -> Transform {
+        let m = Mat4::rotation(axis, degrees);
+        Transform { m, inv: m.transpose() }
+    }
+
+    pub fn rotate_quat(rotation: Quat) -> Transform {
+        let m = rotation.to_mat4();
+        Transform { m, inv: m.transpose() }
+    }
+
+    /* The transform that applies self first and then "next" */
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            m: next.m * self.m,
+            inv: self.inv * next.inv
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform { m: self.inv, inv: self.m }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.m
+    }
+
+    /* Points are affected by the translation part, vectors aren't */
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.m.transform_point(p)
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.m.transform_vector(v)
+    }
+
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inv.transform_normal(n)
+    }
+
+    /* Box around the 8 transformed corners of the given box */
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let mut min = Vec3(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Vec3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+        for x in [bbox.x.min(), bbox.x.max()] {
+            for y in [bbox.y.min(), bbox.y.max()] {
+                for z in [bbox.z.min(), bbox.z.max()] {
+                    let corner = self.point(Vec3(x, y, z));
+                    min = min.min(corner);
+                    max = max.max(corner);
+                }
+            }
+        }
+
+        Aabb::from_points(min, max)
+    }
+}
+
+/*
+** Places any hittable in the world through a transform, without touching the object itself.
+** The ray is moved into object space, and the hit is moved back out, so one (e.g. mesh) object can be instanced many times.
+*/
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    bbox: Aabb
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        Instance {
+            bbox: transform.bounding_box(&object.bounding_box()),
+            object,
+            transform
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        /* The direction isn't normalized, so t means the same thing in both spaces */
+        let inverse = self.transform.inverse();
+        let object_ray = Ray {
+            orig: inverse.point(ray.origin()),
+            dir: inverse.vector(ray.direction())
+        };
+
+        // Determine whether an intersection exists in object space (and if so, where).
+        if !self.object.hit(object_ray, ray_t, hit_record) {
+            return false;
+        }
+
+        /* front_face stays valid: the dot product of a direction and a normal is unchanged by the transform */
+        hit_record.point = self.transform.point(hit_record.point);
+        hit_record.normal = self.transform.normal(hit_record.normal).unit();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert_float_absolute_eq!(a.x(), b.x());
+        assert_float_absolute_eq!(a.y(), b.y());
+        assert_float_absolute_eq!(a.z(), b.z());
+    }
+
+    #[test]
+    fn points_vectors_and_normals() {
+        let transform = Transform::scale(Vec3(2.0, 1.0, 1.0))
+            .then(&Transform::rotate(Vec3(0.0, 1.0, 0.0), 90.0))
+            .then(&Transform::translate(Vec3(0.0, 0.0, -5.0)));
+
+        assert_vec_eq(transform.point(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, -7.0));
+        assert_vec_eq(transform.vector(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, -2.0));
+        assert_vec_eq(transform.inverse().point(Vec3(0.0, 0.0, -7.0)), Vec3(1.0, 0.0, 0.0));
+
+        /* A 45 degree normal of the stretched surface must lean towards the unstretched axis */
+        let normal = Transform::scale(Vec3(2.0, 1.0, 1.0)).normal(Vec3(1.0, 1.0, 0.0));
+        assert_vec_eq(normal, Vec3(0.5, 1.0, 0.0));
+
+        /* Same transform, built from a quaternion and from a raw matrix */
+        let rebuilt = Transform::scale(Vec3(2.0, 1.0, 1.0))
+            .then(&Transform::rotate_quat(Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), 90.0)))
+            .then(&Transform::from_matrix(Mat4::translation(Vec3(0.0, 0.0, -5.0))).unwrap());
+        assert_vec_eq(rebuilt.point(Vec3(1.0, 0.0, 0.0)), Vec3(0.0, 0.0, -7.0));
+        assert_vec_eq(rebuilt.inverse().point(Vec3(0.0, 0.0, -7.0)), Vec3(1.0, 0.0, 0.0));
+        assert!(Transform::from_matrix(Mat4::scaling(Vec3(0.0, 1.0, 1.0))).is_none());
+    }
+
+    #[test]
+    fn instance_hit() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere {
+            center: Vec3(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+        });
+        let instance = Instance::new(sphere, Transform::scale(Vec3(1.0, 1.0, 2.0)).then(&Transform::translate(Vec3(3.0, 0.0, 0.0))));
+
+        let ray = Ray {
+            orig: Vec3(3.0, 0.0, 10.0),
+            dir: Vec3(0.0, 0.0, -1.0)
+        };
+        let mut hit_record = HitRecord::default();
+
+        assert!(instance.hit(ray, Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert_float_absolute_eq!(hit_record.t, 8.0);
+        assert_vec_eq(hit_record.point, Vec3(3.0, 0.0, 2.0));
+        assert_vec_eq(hit_record.normal, Vec3(0.0, 0.0, 1.0));
+        assert!(hit_record.front_face);
+
+        let bbox = instance.bounding_box();
+        assert_float_absolute_eq!(bbox.x.min(), 2.0);
+        assert_float_absolute_eq!(bbox.z.max(), 2.0);
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::Vec3;
+use crate::Ray;
+
+/* 
+** Triangle with optional per-vertex normals and texture coordinates.
+** When they are given, the shading normal and (u, v) are interpolated across the face,
+** so a mesh of flat triangles can look smooth. Without them, the face normal and barycentric coordinates are used.
+*/
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    material: Arc<dyn Material>
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vec3; 3], normals: Option<[Vec3; 3]>, uvs: Option<[(f64, f64); 3]>, material: Arc<dyn Material>) -> Triangle {
+        Triangle { vertices, normals, uvs, material }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: Ray, ray_t: Interval, hit_record: &mut HitRecord ) -> bool {
+        let Some((t, b1, b2)) = intersect(ray, ray_t, self.vertices) else {
+            return false;
+        };
+
+        set_hit_record(hit_record, ray, t, (b1, b2), self.vertices, self.normals, self.uvs);
+        hit_record.material = Some(self.material.clone());
+        hit_record.color = None;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        triangle_bounding_box(self.vertices)
+    }
+}
+
+/*
+** Möller–Trumbore intersection. The hit point is written with barycentric coordinates as
+** p0 + b1 * (p1 - p0) + b2 * (p2 - p0) and solved for (t, b1, b2) with Cramer's rule.
+** Ref: https://www.graphics.cornell.edu/pubs/1997/MT97.pdf
+*/
+pub fn intersect(ray: Ray, ray_t: Interval, p: [Vec3; 3]) -> Option<(f64, f64, f64)> {
+    let edge1 = p[1] - p[0];
+    let edge2 = p[2] - p[0];
+
+    let pvec = ray.direction().cross(edge2);
+    let det = edge1.dot(pvec);
+
+    // No hit if the ray is parallel to the triangle plane.
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin() - p[0];
+    let b1 = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(edge1);
+    let b2 = ray.direction().dot(qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(qvec) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+/* Fills in everything but the material of a triangle hit */
+pub fn set_hit_record(hit_record: &mut HitRecord, ray: Ray, t: f64, (b1, b2): (f64, f64), p: [Vec3; 3], normals: Option<[Vec3; 3]>, uvs: Option<[(f64, f64); 3]>) {
+    let b0 = 1.0 - b1 - b2;
+
+    hit_record.t = t;
+    hit_record.point = ray.at(t);
+
+    /* front_face always comes from the geometric normal, which is what the ray really crosses */
+    let geometric_normal = (p[1] - p[0]).cross(p[2] - p[0]).unit();
+    hit_record.set_face_normal(ray, geometric_normal);
+
+    if let Some(n) = normals {
+        let mut shading_normal = (b0 * n[0] + b1 * n[1] + b2 * n[2]).unit();
+        // Keep the shading normal on the same side as the geometric one.
+        if shading_normal.dot(geometric_normal) < 0.0 {
+            shading_normal = -shading_normal;
+        }
+        hit_record.normal = if hit_record.front_face { shading_normal } else { -shading_normal };
+    }
+
+    (hit_record.u, hit_record.v) = match uvs {
+        Some(uv) => (
+            b0 * uv[0].0 + b1 * uv[1].0 + b2 * uv[2].0,
+            b0 * uv[0].1 + b1 * uv[1].1 + b2 * uv[2].1
+        ),
+        None => (b1, b2)
+    };
+}
+
+pub fn triangle_bounding_box(p: [Vec3; 3]) -> Aabb {
+    Aabb::enclosing(&Aabb::from_points(p[0], p[1]), &Aabb::from_points(p[0], p[2]))
+}
+
+/*******************/
+// TEST SECTION
+/*******************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    const VERTICES: [Vec3; 3] = [Vec3(0.0, 0.0, -1.0), Vec3(1.0, 0.0, -1.0), Vec3(0.0, 1.0, -1.0)];
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn ray_towards(x: f64, y: f64) -> Ray {
+        Ray {
+            orig: Vec3(x, y, 0.0),
+            dir: Vec3(0.0, 0.0, -1.0)
+        }
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        let triangle = Triangle::new(VERTICES, None, None, material());
+        let mut hit_record = HitRecord::default();
+
+        assert!(triangle.hit(ray_towards(0.25, 0.5), Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert_float_absolute_eq!(hit_record.t, 1.0);
+        /* Without texture coordinates, (u, v) are the barycentric coordinates */
+        assert_float_absolute_eq!(hit_record.u, 0.25);
+        assert_float_absolute_eq!(hit_record.v, 0.5);
+        assert!(hit_record.front_face);
+        assert_eq!(hit_record.normal, Vec3(0.0, 0.0, 1.0));
+
+        assert!(!triangle.hit(ray_towards(0.75, 0.5), Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert!(!triangle.hit(ray_towards(-0.1, 0.5), Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert!(!triangle.hit(ray_towards(0.25, 0.5), Interval(0.001, 0.5), &mut hit_record));
+    }
+
+    #[test]
+    fn interpolated_normals_and_uvs() {
+        let normals = [Vec3(0.0, 0.0, 1.0), Vec3(1.0, 0.0, 1.0).unit(), Vec3(0.0, 1.0, 1.0).unit()];
+        let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)];
+        let triangle = Triangle::new(VERTICES, Some(normals), Some(uvs), material());
+        let mut hit_record = HitRecord::default();
+
+        assert!(triangle.hit(ray_towards(0.5, 0.0), Interval(0.001, f64::INFINITY), &mut hit_record));
+        /* Halfway along the first edge */
+        let expected = (0.5 * normals[0] + 0.5 * normals[1]).unit();
+        assert_float_absolute_eq!(hit_record.normal.x(), expected.x());
+        assert_float_absolute_eq!(hit_record.normal.z(), expected.z());
+        assert_float_absolute_eq!(hit_record.u, 0.5);
+        assert_float_absolute_eq!(hit_record.v, 0.0);
+
+        /* From behind, the shading normal is flipped towards the ray too */
+        let behind = Ray {
+            orig: Vec3(0.5, 0.0, -2.0),
+            dir: Vec3(0.0, 0.0, 1.0)
+        };
+        assert!(triangle.hit(behind, Interval(0.001, f64::INFINITY), &mut hit_record));
+        assert!(!hit_record.front_face);
+        assert!(hit_record.normal.z() < 0.0);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let bbox = Triangle::new(VERTICES, None, None, material()).bounding_box();
+
+        assert_float_absolute_eq!(bbox.x.max(), 1.0);
+        assert_float_absolute_eq!(bbox.y.max(), 1.0);
+        assert!(bbox.z.size() > 0.0);
+    }
+}
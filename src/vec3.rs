@@ -1,5 +1,6 @@
-use std::{cell::Ref, ops::{Add, Div, Mul, Sub}};
-use crate::random::{random, random_within};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+use crate::random::Rng;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Vec3(pub f64, pub f64, pub f64);
@@ -29,9 +30,12 @@ impl Vec3 {
         )
     }
 
-    fn length(&self) -> f64 {
-        let squared = self.0 * self.0 + self.1 * self.1 + self.2 * self.2;
-        squared.sqrt()
+    pub fn length(&self) -> f64 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(&self) -> f64 {
+        self.0 * self.0 + self.1 * self.1 + self.2 * self.2
     }
 
     pub fn unit(self) -> Vec3 {
@@ -39,12 +43,22 @@ impl Vec3 {
         self / length
     }
 
-    fn random() -> Vec3 {
-        Vec3(random(), random(), random())
+    /* Component-wise minimum and maximum */
+    pub fn min(self, u: Vec3) -> Vec3 {
+        Vec3(self.0.min(u.0), self.1.min(u.1), self.2.min(u.2))
+    }
+
+    pub fn max(self, u: Vec3) -> Vec3 {
+        Vec3(self.0.max(u.0), self.1.max(u.1), self.2.max(u.2))
+    }
+
+    pub fn abs(self) -> Vec3 {
+        Vec3(self.0.abs(), self.1.abs(), self.2.abs())
     }
 
-    fn random_within(min: f64, max: f64) -> Vec3 {
-        Vec3(random_within(min, max), random_within(min, max), random_within(min, max))
+    /* Linear interpolation: t = 0 gives self, t = 1 gives u */
+    pub fn lerp(self, u: Vec3, t: f64) -> Vec3 {
+        (1.0 - t) * self + t * u
     }
 
     /* 
@@ -54,9 +68,9 @@ impl Vec3 {
     ** On the discussion about why we discard rays outside the sphere:
     ** https://github.com/RayTracing/raytracing.github.io/discussions/1369
      */
-    pub fn random_unit() -> Vec3 {
+    pub fn random_unit(rng: &mut Rng) -> Vec3 {
         loop {
-            let reflected = Self::random_within(-1.0, 1.0);
+            let reflected = Vec3(rng.random_within(-1.0, 1.0), rng.random_within(-1.0, 1.0), rng.random_within(-1.0, 1.0));
             let len = reflected.length();
             /* The first comparison is to handle a small floating-point abstraction leak */
             if 1e-160 < len && len <= 1. {
@@ -68,21 +82,21 @@ impl Vec3 {
     pub fn near_zero(self) -> bool {
         // Return true if the vector is close to zero in all dimensions.
         let s = 1e-8;
-        return (self.0.abs() < s) && (self.1.abs() < s) && (self.2.abs() < s) 
+        (self.0.abs() < s) && (self.1.abs() < s) && (self.2.abs() < s)
     }
 
     /* Mirror reflection */
     pub fn reflect(&self, normal: &Vec3) -> Vec3 {
-        return self - 2.0 * (self.dot(*normal) * normal);
+        self - 2.0 * (self.dot(*normal) * normal)
     }
 
     pub fn refract(&self, normal: Vec3, refraction_ratio: f64) -> Vec3 {
         let cos_theta = -self.dot(normal).min(1.0);
         let refracted_perpendicular = refraction_ratio * (self + cos_theta * normal);
         /* The book has an "abs" around the subtraction, despite the book formular has not */
-        let refracted_paralell = - (1.0 - refracted_perpendicular.length().powi(2)).abs().sqrt() * normal;
+        let refracted_paralell = - (1.0 - refracted_perpendicular.length_squared()).abs().sqrt() * normal;
 
-        return refracted_paralell + refracted_perpendicular;
+        refracted_paralell + refracted_perpendicular
     }
 
 }
@@ -299,14 +313,127 @@ impl Div<f64> for Vec3 {
     type Output = Vec3;
 }
 
+impl Mul<f64> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: f64) -> Vec3 {
+        Vec3(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
+
+impl Div<f64> for &Vec3 {
+    type Output = Vec3;
+
+    fn div(self, rhs: f64) -> Vec3 {
+        Vec3(self.0 / rhs, self.1 / rhs, self.2 / rhs)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3(-self.0, -self.1, -self.2)
+    }
+}
+
+impl Neg for &Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3(-self.0, -self.1, -self.2)
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, rhs: Vec3) {
+        self.0 += rhs.0;
+        self.1 += rhs.1;
+        self.2 += rhs.2;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, rhs: Vec3) {
+        self.0 -= rhs.0;
+        self.1 -= rhs.1;
+        self.2 -= rhs.2;
+    }
+}
+
+/* Component-wise, like Mul for two vectors */
+impl MulAssign for Vec3 {
+    fn mul_assign(&mut self, rhs: Vec3) {
+        self.0 *= rhs.0;
+        self.1 *= rhs.1;
+        self.2 *= rhs.2;
+    }
+}
+
+impl MulAssign<f64> for Vec3 {
+    fn mul_assign(&mut self, rhs: f64) {
+        self.0 *= rhs;
+        self.1 *= rhs;
+        self.2 *= rhs;
+    }
+}
+
+impl DivAssign for Vec3 {
+    fn div_assign(&mut self, rhs: Vec3) {
+        self.0 /= rhs.0;
+        self.1 /= rhs.1;
+        self.2 /= rhs.2;
+    }
+}
+
+impl DivAssign<f64> for Vec3 {
+    fn div_assign(&mut self, rhs: f64) {
+        self.0 /= rhs;
+        self.1 /= rhs;
+        self.2 /= rhs;
+    }
+}
+
+/* v[0], v[1] and v[2] are x, y and z. Handy when looping over the axes */
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => panic!("Vec3 index out of range: {axis}")
+        }
+    }
+}
+
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        match axis {
+            0 => &mut self.0,
+            1 => &mut self.1,
+            2 => &mut self.2,
+            _ => panic!("Vec3 index out of range: {axis}")
+        }
+    }
+}
+
+impl Sum for Vec3 {
+    fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Vec3 {
+        iter.fold(Vec3::default(), |acc, v| acc + v)
+    }
+}
+
+impl<'a> Sum<&'a Vec3> for Vec3 {
+    fn sum<I: Iterator<Item = &'a Vec3>>(iter: I) -> Vec3 {
+        iter.fold(Vec3::default(), |acc, v| acc + v)
+    }
+}
+
 impl PartialEq for Vec3 {
     fn eq(&self, other: &Self) -> bool {
-        if self.0 == other.0 && self.1 == other.1 && self.2 == other.2 {
-            return true;
-        }
-        else {
-            return false;
-        };
+        self.0 == other.0 && self.1 == other.1 && self.2 == other.2
     }
 }
 
@@ -350,7 +477,7 @@ mod tests {
         let v2 = Vec3(1.0, 1.5, 2.0);
 
         let v3 = v1 * v2;
-        let v4 = 1.5 * v3.clone();
+        let v4 = 1.5 * v3;
 
         assert_eq!(v3, Vec3(2.0, 9.0, 18.0));
         assert_eq!(v4, Vec3(3.0, 13.5, 27.0));
@@ -364,12 +491,56 @@ mod tests {
         let v2 = Vec3(2.0, 2.0, 2.0);
 
         let v3 = v1 / v2;
-        let v4 = v3.clone() / 2.0;
+        let v4 = v3 / 2.0;
 
         assert_eq!(v3, Vec3(1.0, 3.0, 4.5));
         assert_eq!(v4, Vec3(0.5, 1.5, 2.25));
     }
 
+    #[test]
+    fn negation_and_assignment() {
+        let mut v = Vec3(2.0, -6.0, 9.0);
+
+        assert_eq!(-v, Vec3(-2.0, 6.0, -9.0));
+
+        v += Vec3(1.0, 1.0, 1.0);
+        assert_eq!(v, Vec3(3.0, -5.0, 10.0));
+        v -= Vec3(1.0, 1.0, 2.0);
+        assert_eq!(v, Vec3(2.0, -6.0, 8.0));
+        v *= 0.5;
+        assert_eq!(v, Vec3(1.0, -3.0, 4.0));
+        v *= Vec3(2.0, 1.0, 0.5);
+        assert_eq!(v, Vec3(2.0, -3.0, 2.0));
+        v /= 2.0;
+        assert_eq!(v, Vec3(1.0, -1.5, 1.0));
+        v /= Vec3(1.0, -1.5, 4.0);
+        assert_eq!(v, Vec3(1.0, 1.0, 0.25));
+    }
+
+    #[test]
+    fn index_and_sum() {
+        let mut v = Vec3(1.0, 2.0, 3.0);
+        v[2] = 5.0;
+
+        assert_eq!((v[0], v[1], v[2]), (1.0, 2.0, 5.0));
+
+        let vectors = [Vec3(1.0, 2.0, 3.0), Vec3(0.5, 0.5, 0.5), Vec3(-1.0, 0.0, 1.0)];
+        assert_eq!(vectors.iter().sum::<Vec3>(), Vec3(0.5, 2.5, 4.5));
+        assert_eq!(vectors.into_iter().sum::<Vec3>(), Vec3(0.5, 2.5, 4.5));
+    }
+
+    #[test]
+    fn component_wise() {
+        let v1 = Vec3(1.0, -4.0, 3.0);
+        let v2 = Vec3(2.0, -5.0, 0.0);
+
+        assert_eq!(v1.min(v2), Vec3(1.0, -5.0, 0.0));
+        assert_eq!(v1.max(v2), Vec3(2.0, -4.0, 3.0));
+        assert_eq!(v1.abs(), Vec3(1.0, 4.0, 3.0));
+        assert_eq!(v1.lerp(v2, 0.5), Vec3(1.5, -4.5, 1.5));
+        assert_eq!(v1.length_squared(), 26.0);
+    }
+
     #[test]
     fn dot() {
         let v1 = Vec3(1.5, 0.6, 1.2);